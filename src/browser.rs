@@ -0,0 +1,288 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use image::ImageReader;
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+};
+
+use crate::libdatabend::imageio::is_decodable;
+
+/// Which path field the browser fills in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BrowseTarget {
+    Input,
+    Output,
+    /// A grayscale mask for the region.
+    Mask,
+    /// A recipe to load; lists `.toml` files instead of images.
+    Recipe,
+}
+
+struct Entry {
+    name: String,
+    path: PathBuf,
+    is_dir: bool,
+    size: u64,
+}
+
+/// Popup listing the directories and decodable images (or recipes) of one folder.
+pub struct FileBrowser {
+    pub target: BrowseTarget,
+    dir: PathBuf,
+    entries: Vec<Entry>,
+    selected: usize,
+    /// Header details of the highlighted image, read once per selection.
+    details: Option<Result<String, String>>,
+    error: Option<String>,
+}
+
+impl FileBrowser {
+    /// Opens in the folder of `current`, falling back to the working directory.
+    pub fn open(target: BrowseTarget, current: &str) -> Self {
+        let current = Path::new(current);
+        let dir = if current.is_dir() {
+            current.to_path_buf()
+        } else {
+            current
+                .parent()
+                .filter(|parent| parent.is_dir())
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|| PathBuf::from("."))
+        };
+        let mut browser = Self {
+            target,
+            dir: fs::canonicalize(&dir).unwrap_or(dir),
+            entries: Vec::new(),
+            selected: 0,
+            details: None,
+            error: None,
+        };
+        browser.read_dir();
+        let current = fs::canonicalize(current).ok();
+        if let Some(index) = browser
+            .entries
+            .iter()
+            .position(|entry| Some(&entry.path) == current.as_ref())
+        {
+            browser.select(index);
+        }
+        browser
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn next(&mut self) {
+        if !self.entries.is_empty() {
+            self.select((self.selected + 1) % self.entries.len());
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if !self.entries.is_empty() {
+            self.select(
+                self.selected
+                    .checked_sub(1)
+                    .unwrap_or(self.entries.len() - 1),
+            );
+        }
+    }
+
+    /// Enters the highlighted directory, or returns the highlighted file.
+    pub fn activate(&mut self) -> Option<PathBuf> {
+        let entry = self.entries.get(self.selected)?;
+        if entry.is_dir {
+            self.dir = fs::canonicalize(&entry.path).unwrap_or_else(|_| entry.path.clone());
+            self.read_dir();
+            None
+        } else {
+            Some(entry.path.clone())
+        }
+    }
+
+    pub fn parent(&mut self) {
+        let Some(parent) = self.dir.parent().map(Path::to_path_buf) else {
+            return;
+        };
+        let previous = std::mem::replace(&mut self.dir, parent);
+        self.read_dir();
+        if let Some(index) = self.entries.iter().position(|entry| entry.path == previous) {
+            self.select(index);
+        }
+    }
+
+    /// Lists subdirectories and the files `image` can decode, or recipe files,
+    /// folders first. Hidden entries are skipped.
+    fn read_dir(&mut self) {
+        self.entries.clear();
+        self.error = None;
+        match fs::read_dir(&self.dir) {
+            Ok(read_dir) => {
+                for dir_entry in read_dir.flatten() {
+                    let name = dir_entry.file_name().to_string_lossy().into_owned();
+                    let Ok(metadata) = fs::metadata(dir_entry.path()) else {
+                        continue;
+                    };
+                    let path = dir_entry.path();
+                    let listed = match self.target {
+                        BrowseTarget::Recipe => is_recipe(&path),
+                        BrowseTarget::Input | BrowseTarget::Output | BrowseTarget::Mask => {
+                            is_decodable(&path)
+                        }
+                    };
+                    if name.starts_with('.') || !(metadata.is_dir() || listed) {
+                        continue;
+                    }
+                    self.entries.push(Entry {
+                        name,
+                        path,
+                        is_dir: metadata.is_dir(),
+                        size: metadata.len(),
+                    });
+                }
+            }
+            Err(error) => self.error = Some(error.to_string()),
+        }
+        self.entries.sort_by(|a, b| {
+            b.is_dir
+                .cmp(&a.is_dir)
+                .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+        });
+        if let Some(parent) = self.dir.parent() {
+            self.entries.insert(
+                0,
+                Entry {
+                    name: "..".to_string(),
+                    path: parent.to_path_buf(),
+                    is_dir: true,
+                    size: 0,
+                },
+            );
+        }
+        self.select(0);
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index;
+        self.details = self
+            .entries
+            .get(index)
+            .filter(|entry| !entry.is_dir && self.target != BrowseTarget::Recipe)
+            .map(|entry| image_details(&entry.path, entry.size));
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect) {
+        f.render_widget(Clear, area);
+        let title = match self.target {
+            BrowseTarget::Input => "Choose Input",
+            BrowseTarget::Output => "Choose Output",
+            BrowseTarget::Mask => "Choose Mask",
+            BrowseTarget::Recipe => "Choose Recipe",
+        };
+        let block = Block::default()
+            .title(format!("{}: {}", title, self.dir.display()))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow));
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(inner);
+
+        let items: Vec<ListItem> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                if entry.is_dir {
+                    ListItem::new(Line::from(Span::styled(
+                        format!("📁 {}/", entry.name),
+                        Style::default().fg(Color::Cyan),
+                    )))
+                } else if self.target == BrowseTarget::Recipe {
+                    ListItem::new(format!("📜 {}", entry.name))
+                } else {
+                    ListItem::new(format!("🖼  {}", entry.name))
+                }
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::RIGHT))
+            .highlight_style(Style::default().bg(Color::Blue).fg(Color::White))
+            .highlight_symbol("▶ ");
+        let mut state = ListState::default().with_selected(Some(self.selected));
+        f.render_stateful_widget(list, chunks[0], &mut state);
+
+        let details = match (&self.error, &self.details) {
+            (Some(error), _) => Text::styled(
+                format!("Can't read folder: {}", error),
+                Style::default().fg(Color::Red),
+            ),
+            (None, Some(Ok(details))) => Text::raw(details.as_str()),
+            (None, Some(Err(error))) => Text::styled(
+                format!("Can't read image: {}", error),
+                Style::default().fg(Color::Red),
+            ),
+            (None, None) => Text::styled(
+                if self.target == BrowseTarget::Recipe {
+                    "Folders and recipe files (.toml)"
+                } else {
+                    "Folders and images the app can decode"
+                },
+                Style::default()
+                    .fg(Color::DarkGray)
+                    .add_modifier(Modifier::ITALIC),
+            ),
+        };
+        f.render_widget(Paragraph::new(details).wrap(Wrap { trim: true }), chunks[1]);
+    }
+}
+
+fn is_recipe(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("toml"))
+}
+
+/// Format, dimensions and file size, read from the image header only.
+fn image_details(path: &Path, size: u64) -> Result<String, String> {
+    let reader = ImageReader::open(path)
+        .and_then(ImageReader::with_guessed_format)
+        .map_err(|error| error.to_string())?;
+    let format = reader
+        .format()
+        .map_or("unknown".to_string(), |format| format!("{:?}", format));
+    let (width, height) = reader
+        .into_dimensions()
+        .map_err(|error| error.to_string())?;
+    Ok(format!(
+        "{}\n{} × {} px\n{}",
+        format,
+        width,
+        height,
+        human_size(size)
+    ))
+}
+
+fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
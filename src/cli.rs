@@ -0,0 +1,1020 @@
+use std::{io, path::Path};
+
+use image::Rgb;
+
+use crate::libdatabend::{
+    self,
+    batch::{self, BatchReport},
+    effect::{Effect, ParamKind, ParamRanges, ParamSpec, ParamValues},
+    error::DatabendError,
+    filebend::{self, Bent, FileBender},
+    imageio::{self, SaveOptions},
+    keylog::{self, KeyLog},
+    pipeline::{Pipeline, PipelineStep},
+    progress::Progress,
+    recipe::Recipe,
+    region::Region,
+    sequence::{self, FramePattern, SeedMode},
+};
+
+const USAGE: &str = "\
+Usage:
+  shutterbomb                     Start the interactive TUI
+  shutterbomb list                List the effects available on the command line
+  shutterbomb apply <effect> [--<param> <value>]... [--seed <n>] [--background <RRGGBB>]
+                  [--region <spec> [--feather <px>]] <input> <output>
+  shutterbomb bend <mode> [--<param> <value>]... [--seed <n>] <input> <output>
+  shutterbomb batch apply <effect> | bend <mode> [options]... [--template <name>]
+                  <input dir, glob or files>... <output dir>
+  shutterbomb sequence apply <effect> | bend <mode> [--<param> <start>..<end>]...
+                  [--seed-mode stable|flicker] <input pattern> <output pattern or dir>
+  shutterbomb --recipe <file> [--seed <n>] [--background <RRGGBB>] <input> <output>
+  shutterbomb replay <key log> [--size <w>x<h> | <n>%] [--background <RRGGBB>]
+                  [--region <spec> [--feather <px>]] [<input>] <output>
+  shutterbomb help                Show this message
+
+Batch outputs are named by --template (default: {stem}_bent.{ext}); {stem},
+{ext} and {name} come from each input. Failed images are reported and skipped.
+
+Sequences read numbered frames such as frame_%05d.png. A <start>..<end> value
+is interpolated from the first frame to the last; --seed-mode stable (default)
+reuses the seed on every frame, flicker adds the frame number to it.
+
+A recipe file can stand in for `apply <effect>` or `bend <mode>`, also after
+batch or sequence. --seed and --background override the recipe's own.
+
+--region keeps any effect to part of the image: x,y,w,h rectangles in pixels
+or percent, separated by ';', or a grayscale mask image whose brightness sets
+the strength. --feather fades the edge over that many pixels. Bent files are
+decoded to blend them, so the output holds the blended image.
+
+Interactive sessions leave a key log next to their output ({stem}_keys.log).
+replay plays it back with the same seed on the logged image, or on <input>
+scaled to the logged size; --size sets another size, a percentage of the
+logged one.
+
+Example:
+  shutterbomb apply oversensibility --iso 3200 in.jpg out.png
+  shutterbomb bend jpeg --amount 200 --decode yes in.jpg bent.jpg
+  shutterbomb batch apply overexposure --seed 7 'shoot/*.jpg' bent/
+  shutterbomb sequence apply overexposure --exposure 0.5..2.0 'clip/frame_%05d.png' bent/
+  shutterbomb batch --recipe glitchy.toml shoot/ bent/
+  shutterbomb apply overexposure --region '0,0,100%,40%' --feather 20 in.jpg sky.png
+  shutterbomb replay out_keys.log --size 200% other.jpg big.png";
+
+/// Exit code for a failed effect run.
+const EXIT_FAILURE: i32 = 1;
+/// Exit code for malformed arguments.
+const EXIT_USAGE: i32 = 2;
+
+enum Command {
+    List,
+    Help,
+    Apply {
+        effect: &'static dyn Effect,
+        args: RunArgs,
+    },
+    Bend {
+        bender: &'static dyn FileBender,
+        args: RunArgs,
+    },
+    /// A recipe of several effects, run in memory one after another.
+    Chain {
+        pipeline: Pipeline,
+        args: RunArgs,
+    },
+    /// An `apply`, `bend` or chain command run over many inputs.
+    Batch(Box<Command>),
+    /// An `apply` or `bend` command run over numbered frames.
+    Sequence(Box<Command>),
+    Replay(ReplayArgs),
+}
+
+/// How an `apply`/`bend` line is run, which decides the options it takes.
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Single,
+    Batch,
+    Sequence,
+}
+
+/// Everything after the command (and effect name) on an `apply`/`bend` line.
+struct RunArgs {
+    values: ParamValues,
+    /// Where `start..end` values end up on the last frame of a sequence.
+    end_values: Option<ParamValues>,
+    seed: Option<u64>,
+    seed_mode: Option<SeedMode>,
+    options: SaveOptions,
+    template: Option<String>,
+    /// Exactly one file or frame pattern, except in batches.
+    input_paths: Vec<String>,
+    output_path: String,
+}
+
+/// Everything after `replay`.
+struct ReplayArgs {
+    log_path: String,
+    /// Plays the log onto this image instead of the one it was recorded on.
+    input_path: Option<String>,
+    size: Option<ReplaySize>,
+    background: Option<Rgb<u8>>,
+    /// Replaces the region the log was recorded with.
+    region: Option<Region>,
+    output_path: String,
+}
+
+/// Why a command line was turned down before anything ran.
+enum ArgError {
+    /// The line itself is malformed: an unknown command or option, or a
+    /// missing argument. Printed with the usage text.
+    Usage(String),
+    /// The line is well-formed, but a value in it or the recipe it names is
+    /// unusable. Printed on its own.
+    Invalid(DatabendError),
+}
+
+impl From<String> for ArgError {
+    fn from(message: String) -> Self {
+        ArgError::Usage(message)
+    }
+}
+
+impl From<&str> for ArgError {
+    fn from(message: &str) -> Self {
+        ArgError::Usage(message.to_string())
+    }
+}
+
+impl From<DatabendError> for ArgError {
+    fn from(error: DatabendError) -> Self {
+        ArgError::Invalid(error)
+    }
+}
+
+/// The resolution a replay runs at.
+#[derive(Clone, Copy)]
+enum ReplaySize {
+    Pixels(u32, u32),
+    Percent(u32),
+}
+
+/// Runs a headless command and returns the process exit code.
+pub fn run(args: &[String]) -> i32 {
+    let command = match parse(args) {
+        Ok(command) => command,
+        Err(ArgError::Usage(message)) => {
+            eprintln!("shutterbomb: {}\n\n{}", message, USAGE);
+            return EXIT_USAGE;
+        }
+        Err(ArgError::Invalid(error)) => {
+            eprintln!("shutterbomb: {}", error);
+            return EXIT_FAILURE;
+        }
+    };
+
+    match command {
+        Command::Help => {
+            println!("{}", USAGE);
+            0
+        }
+        Command::List => {
+            list();
+            0
+        }
+        Command::Apply { effect, args } => report(
+            &args,
+            imageio::apply_file(
+                effect,
+                &args.input_paths[0],
+                &args.output_path,
+                &args.values,
+                args.seed,
+                &args.options,
+                &Progress::none(),
+            ),
+        ),
+        Command::Bend { bender, args } => report(
+            &args,
+            filebend::main(
+                bender,
+                &args.input_paths[0],
+                &args.output_path,
+                &args.values,
+                args.seed,
+                &args.options,
+                &Progress::none(),
+            )
+            .map(warn_copy),
+        ),
+        Command::Chain { pipeline, args } => report(
+            &args,
+            pipeline.apply_file(
+                &args.input_paths[0],
+                &args.output_path,
+                args.seed,
+                &args.options,
+                &Progress::none(),
+            ),
+        ),
+        Command::Batch(command) => run_batch(&command),
+        Command::Sequence(command) => run_sequence(&command),
+        Command::Replay(args) => match replay(&args) {
+            Ok(line) => {
+                println!("{}", line);
+                0
+            }
+            Err(message) => {
+                eprintln!("shutterbomb: {}", message);
+                EXIT_FAILURE
+            }
+        },
+    }
+}
+
+/// Prints why a bend is missing its decoded copy, if it is, and passes on
+/// the seed: the bent file itself was written.
+fn warn_copy(bent: Bent) -> u64 {
+    if let Some(warning) = bent.warning() {
+        eprintln!("shutterbomb: warning: {}", warning);
+    }
+    bent.seed
+}
+
+fn report(args: &RunArgs, result: Result<u64, DatabendError>) -> i32 {
+    match result {
+        Ok(used_seed) => {
+            println!(
+                "{} -> {} (seed {})",
+                args.input_paths[0], args.output_path, used_seed
+            );
+            0
+        }
+        Err(error) => {
+            eprintln!("shutterbomb: {}", error);
+            EXIT_FAILURE
+        }
+    }
+}
+
+/// Runs an `apply`, `bend` or chain command over every input, printing each
+/// image as it is written and a summary with the failures at the end.
+fn run_batch(command: &Command) -> i32 {
+    let (Command::Apply { args, .. } | Command::Bend { args, .. } | Command::Chain { args, .. }) =
+        command
+    else {
+        return EXIT_USAGE;
+    };
+    let mut inputs = Vec::new();
+    for input in &args.input_paths {
+        match batch::expand(input) {
+            Ok(paths) => inputs.extend(paths),
+            Err(error) => {
+                eprintln!("shutterbomb: {}", error);
+                return EXIT_FAILURE;
+            }
+        }
+    }
+
+    let template = args.template.as_deref().unwrap_or(batch::DEFAULT_TEMPLATE);
+    let result = batch::run(
+        &inputs,
+        Path::new(&args.output_path),
+        template,
+        &Progress::none(),
+        |input, output, _| {
+            let result = run_file(command, input, output, &args.values, args.seed);
+            if let Ok(seed) = result {
+                println!(
+                    "{} -> {} (seed {})",
+                    input.display(),
+                    output.display(),
+                    seed
+                );
+            }
+            result
+        },
+    );
+    match result {
+        Ok(report) => summarize(&report, "images"),
+        Err(error) => {
+            eprintln!("shutterbomb: {}", error);
+            EXIT_FAILURE
+        }
+    }
+}
+
+/// Runs an `apply` or `bend` command over numbered frames, printing each
+/// frame as it is written and a summary with the failures at the end.
+fn run_sequence(command: &Command) -> i32 {
+    let (specs, args) = match command {
+        Command::Apply { effect, args } => (effect.params(), args),
+        Command::Bend { bender, args } => (bender.params(), args),
+        _ => return EXIT_USAGE,
+    };
+    let pattern = match FramePattern::parse(&args.input_paths[0]) {
+        Ok(pattern) => pattern,
+        Err(error) => {
+            eprintln!("shutterbomb: {}", error);
+            return EXIT_FAILURE;
+        }
+    };
+    let end_values = args
+        .end_values
+        .clone()
+        .unwrap_or_else(|| args.values.clone());
+    let ranges = ParamRanges::new(specs, args.values.clone(), end_values);
+    let seed_mode = args.seed_mode.unwrap_or_default();
+    let result = sequence::run(
+        &pattern,
+        &args.output_path,
+        &ranges,
+        args.seed,
+        seed_mode,
+        &Progress::none(),
+        |input, output, values, seed, _| {
+            let result = run_file(command, input, output, values, Some(seed));
+            if let Ok(seed) = result {
+                println!(
+                    "{} -> {} (seed {})",
+                    input.display(),
+                    output.display(),
+                    seed
+                );
+            }
+            result
+        },
+    );
+    match result {
+        Ok(report) => {
+            println!("\nbase seed {} ({})", report.seed, seed_mode.name());
+            summarize(&report.frames, "frames")
+        }
+        Err(error) => {
+            eprintln!("shutterbomb: {}", error);
+            EXIT_FAILURE
+        }
+    }
+}
+
+/// Runs an `apply`, `bend` or chain command from `input` to `output`. `values`
+/// replace the ones on the command line; chains keep their own.
+fn run_file(
+    command: &Command,
+    input: &Path,
+    output: &Path,
+    values: &ParamValues,
+    seed: Option<u64>,
+) -> Result<u64, DatabendError> {
+    let (input, output) = (&*input.to_string_lossy(), &*output.to_string_lossy());
+    match command {
+        Command::Apply { effect, args } => {
+            let progress = Progress::none();
+            imageio::apply_file(
+                *effect,
+                input,
+                output,
+                values,
+                seed,
+                &args.options,
+                &progress,
+            )
+        }
+        Command::Bend { bender, args } => filebend::main(
+            *bender,
+            input,
+            output,
+            values,
+            seed,
+            &args.options,
+            &Progress::none(),
+        )
+        .map(warn_copy),
+        Command::Chain { pipeline, args } => {
+            pipeline.apply_file(input, output, seed, &args.options, &Progress::none())
+        }
+        _ => unreachable!("batches and sequences only wrap apply, bend and chains"),
+    }
+}
+
+/// Plays a key log back with its own seed and settings, and saves the
+/// result. Returns the line to print.
+fn replay(args: &ReplayArgs) -> Result<String, String> {
+    let log = KeyLog::load(&args.log_path, libdatabend::session_params)
+        .map_err(|error| format!("{}: {}", args.log_path, error))?;
+    let input = args.input_path.as_deref().unwrap_or(&log.input);
+    let mut state = libdatabend::start_session(&log.effect, input, Some(log.seed), &log.params)
+        .ok_or_else(|| format!("unknown interactive effect '{}'", log.effect))?
+        .map_err(|error| error.to_string())?;
+
+    // Keys land by buffer position, so the canvas must match the logged size
+    let canvas = state.canvas_mut();
+    let logged = (log.width, log.height);
+    let (width, height) = match args.size {
+        Some(ReplaySize::Pixels(width, height)) => (width, height),
+        Some(ReplaySize::Percent(percent)) => {
+            let scale = |side: u32| (side as u64 * percent as u64 / 100).max(1) as u32;
+            (scale(log.width), scale(log.height))
+        }
+        None if args.input_path.is_none() && (canvas.width, canvas.height) != logged => {
+            return Err(format!(
+                "{} is {}x{}, but the log was recorded at {}x{}; pass --size to replay it anyway",
+                input, canvas.width, canvas.height, log.width, log.height
+            ));
+        }
+        None => logged,
+    };
+    if (canvas.width, canvas.height) != (width, height) {
+        canvas
+            .resize(width, height)
+            .map_err(|error| error.to_string())?;
+    }
+    if let Some(region) = args.region.as_ref().or(log.region.as_ref()) {
+        state
+            .canvas_mut()
+            .set_region(region)
+            .map_err(|error| error.to_string())?;
+    }
+    log.replay(state.as_mut());
+
+    let options = SaveOptions {
+        background: args.background.or(log.background),
+        ..SaveOptions::default()
+    };
+    state
+        .save(&args.output_path, &options)
+        .map_err(|error| error.to_string())?;
+    let canvas = state.canvas();
+    Ok(format!(
+        "{} -> {} ({} keys at {}x{}, seed {})",
+        input,
+        args.output_path,
+        log.strokes.len(),
+        canvas.width,
+        canvas.height,
+        log.seed
+    ))
+}
+
+/// Prints how the run went; any failed image makes the exit code a failure.
+fn summarize(report: &BatchReport, noun: &str) -> i32 {
+    println!(
+        "\n{} of {} {} bent, {} failed",
+        report.succeeded(),
+        report.outcomes.len(),
+        noun,
+        report.failed()
+    );
+    for outcome in &report.outcomes {
+        if let Err(error) = &outcome.result {
+            eprintln!("  failed: {}: {}", outcome.input.display(), error);
+        }
+    }
+    if report.failed() == 0 {
+        0
+    } else {
+        EXIT_FAILURE
+    }
+}
+
+fn list() {
+    for effect in libdatabend::EFFECTS {
+        println!("{}", effect.name());
+        print_params(effect.params());
+    }
+    println!("\nFile-level benders (shutterbomb bend <mode>):");
+    for bender in libdatabend::BENDERS {
+        println!("{}", bender.name());
+        print_params(bender.params());
+    }
+}
+
+fn print_params(specs: &[ParamSpec]) {
+    for spec in specs {
+        let kind = match spec.kind {
+            ParamKind::Int { .. } => "int",
+            ParamKind::Float { .. } => "float",
+            ParamKind::Choice(_) => "choice",
+        };
+        println!(
+            "    --{:<12} {:<6} {} [default: {}]",
+            spec.name,
+            kind,
+            spec.display_label(),
+            spec.format_value(spec.default_value())
+        );
+    }
+}
+
+fn parse(args: &[String]) -> Result<Command, ArgError> {
+    match args.first().map(String::as_str) {
+        Some("list") => Ok(Command::List),
+        Some("help" | "-h" | "--help") => Ok(Command::Help),
+        Some("apply") => check_mode(parse_apply(&args[1..])?, Mode::Single),
+        Some("bend") => check_mode(parse_bend(&args[1..])?, Mode::Single),
+        Some("--recipe") => check_mode(parse_recipe(args)?, Mode::Single),
+        Some("batch") => Ok(Command::Batch(Box::new(parse_wrapped(
+            &args[1..],
+            Mode::Batch,
+        )?))),
+        Some("sequence") => Ok(Command::Sequence(Box::new(parse_wrapped(
+            &args[1..],
+            Mode::Sequence,
+        )?))),
+        Some("replay") => parse_replay(&args[1..]),
+        Some(other) => Err(format!("unknown command '{}'", other).into()),
+        None => Ok(Command::Help),
+    }
+}
+
+fn parse_apply(args: &[String]) -> Result<Command, ArgError> {
+    let name = args.first().ok_or("missing effect name")?;
+    let effect = libdatabend::find_effect(name).ok_or_else(|| {
+        format!(
+            "unknown effect '{}' (run `shutterbomb list` to see them)",
+            name
+        )
+    })?;
+    let args = parse_run_args(effect.name(), effect.params(), &args[1..])?;
+    Ok(Command::Apply { effect, args })
+}
+
+fn parse_bend(args: &[String]) -> Result<Command, ArgError> {
+    let name = args.first().ok_or("missing bend mode")?;
+    let bender = libdatabend::find_bender(name).ok_or_else(|| {
+        format!(
+            "unknown bend mode '{}' (run `shutterbomb list` to see them)",
+            name
+        )
+    })?;
+    let args = parse_run_args(bender.name(), bender.params(), &args[1..])?;
+    Ok(Command::Bend { bender, args })
+}
+
+/// Parses the `apply <effect>`, `bend <mode>` or `--recipe <file>` after
+/// `batch` or `sequence`.
+fn parse_wrapped(args: &[String], mode: Mode) -> Result<Command, ArgError> {
+    let command = match args.first().map(String::as_str) {
+        Some("apply") => parse_apply(&args[1..])?,
+        Some("bend") => parse_bend(&args[1..])?,
+        Some("--recipe") => parse_recipe(args)?,
+        _ => {
+            let name = if mode == Mode::Batch {
+                "batch"
+            } else {
+                "sequence"
+            };
+            return Err(format!(
+                "{} needs `apply <effect>`, `bend <mode>` or `--recipe <file>`",
+                name
+            )
+            .into());
+        }
+    };
+    check_mode(command, mode)
+}
+
+/// Parses `--recipe <file>` and the options and paths after it. A recipe of
+/// one effect runs like `apply` or `bend` would, several run as a chain.
+fn parse_recipe(args: &[String]) -> Result<Command, ArgError> {
+    let path = args.get(1).ok_or("missing value for --recipe")?;
+    let recipe = Recipe::load(path, |name| {
+        libdatabend::find_effect(name)
+            .map(|effect| effect.params())
+            .or_else(|| libdatabend::find_bender(name).map(|bender| bender.params()))
+    })
+    .map_err(|error| in_file(path, error))?;
+    let mut args = parse_run_args("a recipe", &[], &args[2..])?;
+    args.seed = args.seed.or(recipe.seed);
+    args.options.background = args.options.background.or(recipe.background);
+
+    if let [step] = recipe.steps.as_slice() {
+        args.values = step.values.clone();
+        if let Some(effect) = libdatabend::find_effect(&step.effect) {
+            return Ok(Command::Apply { effect, args });
+        }
+        if let Some(bender) = libdatabend::find_bender(&step.effect) {
+            return Ok(Command::Bend { bender, args });
+        }
+    }
+    let mut steps = Vec::new();
+    for step in recipe.steps {
+        let effect = libdatabend::find_effect(&step.effect).ok_or_else(|| {
+            DatabendError::InvalidRecipe(format!(
+                "{}: {} is a file bender and can't be chained with other effects",
+                path, step.effect
+            ))
+        })?;
+        steps.push(PipelineStep {
+            effect,
+            params: step.values,
+            enabled: true,
+        });
+    }
+    Ok(Command::Chain {
+        pipeline: Pipeline { steps },
+        args,
+    })
+}
+
+/// Rejects the options of a parsed `apply`/`bend` command that `mode` has no
+/// use for.
+fn check_mode(command: Command, mode: Mode) -> Result<Command, ArgError> {
+    if let Command::Chain { .. } = &command
+        && mode == Mode::Sequence
+    {
+        let reason = "sequences run one effect, but this recipe is a chain".to_string();
+        return Err(DatabendError::InvalidRecipe(reason).into());
+    }
+    if let Command::Apply { args, .. } | Command::Bend { args, .. } | Command::Chain { args, .. } =
+        &command
+    {
+        if args.template.is_some() && mode != Mode::Batch {
+            return Err("--template only applies to `batch`".into());
+        }
+        if (args.end_values.is_some() || args.seed_mode.is_some()) && mode != Mode::Sequence {
+            return Err("value ranges and --seed-mode only apply to `sequence`".into());
+        }
+        if mode != Mode::Batch && args.input_paths.len() != 1 {
+            return Err("expected exactly one input and one output path".into());
+        }
+    }
+    Ok(command)
+}
+
+/// Parses `replay <key log> [options] [<input>] <output>`.
+fn parse_replay(args: &[String]) -> Result<Command, ArgError> {
+    let mut size = None;
+    let mut background = None;
+    let (mut region, mut feather) = (None, None);
+    let mut positional = Vec::new();
+
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        let Some(flag) = arg.strip_prefix("--") else {
+            positional.push(arg.clone());
+            continue;
+        };
+        let (flag, value) = flag_value(flag, &mut rest)?;
+        match flag {
+            "size" => {
+                size = Some(parse_replay_size(&value).ok_or_else(|| {
+                    let reason = format!("'{}' is not <w>x<h> or <n>%", value);
+                    DatabendError::invalid_parameter("size", reason)
+                })?);
+            }
+            "background" => background = Some(parse_background(&value)?),
+            "region" => region = Some(value),
+            "feather" => feather = Some(parse_feather(&value)?),
+            _ => return Err(format!("replay has no option --{}", flag).into()),
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    let (log_path, input_path, output_path) = match (
+        positional.next(),
+        positional.next(),
+        positional.next(),
+        positional.next(),
+    ) {
+        (Some(log), Some(output), None, None) => (log, None, output),
+        (Some(log), Some(input), Some(output), None) => (log, Some(input), output),
+        _ => return Err("expected a key log, an optional input and an output path".into()),
+    };
+    Ok(Command::Replay(ReplayArgs {
+        log_path,
+        input_path,
+        size,
+        background,
+        region: parse_region(region, feather)?,
+        output_path,
+    }))
+}
+
+fn parse_replay_size(input: &str) -> Option<ReplaySize> {
+    match input.strip_suffix('%') {
+        Some(percent) => percent
+            .parse()
+            .ok()
+            .filter(|&percent| percent > 0)
+            .map(ReplaySize::Percent),
+        None => keylog::parse_size(input).map(|(width, height)| ReplaySize::Pixels(width, height)),
+    }
+}
+
+fn parse_feather(input: &str) -> Result<u32, DatabendError> {
+    input.parse().map_err(|_| {
+        let reason = format!("'{}' is not a whole number of pixels", input);
+        DatabendError::invalid_parameter("feather", reason)
+    })
+}
+
+fn parse_background(input: &str) -> Result<Rgb<u8>, DatabendError> {
+    imageio::parse_color(input).ok_or_else(|| {
+        DatabendError::invalid_parameter(
+            "background",
+            format!("'{}' is not a RRGGBB colour", input),
+        )
+    })
+}
+
+/// Builds the region of `--region` and `--feather`, which needs the former.
+fn parse_region(spec: Option<String>, feather: Option<u32>) -> Result<Option<Region>, ArgError> {
+    match spec {
+        Some(spec) => Ok(Some(Region::parse(&spec, feather.unwrap_or(0))?)),
+        None if feather.is_some() => Err("--feather needs a --region".into()),
+        None => Ok(None),
+    }
+}
+
+/// Names the recipe at `path` in an error from loading it.
+fn in_file(path: &str, error: DatabendError) -> DatabendError {
+    match error {
+        DatabendError::Io(error) => {
+            DatabendError::Io(io::Error::new(error.kind(), format!("{}: {}", path, error)))
+        }
+        DatabendError::InvalidRecipe(reason) => {
+            DatabendError::InvalidRecipe(format!("{}: {}", path, reason))
+        }
+        error => error,
+    }
+}
+
+/// Splits `--flag=value`, or takes the value from the next argument.
+fn flag_value<'a>(
+    flag: &'a str,
+    rest: &mut std::slice::Iter<String>,
+) -> Result<(&'a str, String), String> {
+    match flag.split_once('=') {
+        Some((flag, value)) => Ok((flag, value.to_string())),
+        None => {
+            let value = rest
+                .next()
+                .ok_or_else(|| format!("missing value for --{}", flag))?;
+            Ok((flag, value.clone()))
+        }
+    }
+}
+
+/// Parses `--<param> <value>` (or `<start>..<end>`), `--seed`, `--seed-mode`,
+/// `--background`, `--region`, `--feather`, `--template` and the paths
+/// against the parameter schema of `owner`. The last path is the output.
+fn parse_run_args(owner: &str, specs: &[ParamSpec], args: &[String]) -> Result<RunArgs, ArgError> {
+    let mut values = ParamValues::defaults(specs);
+    let mut ends = Vec::new();
+    let mut seed = None;
+    let mut seed_mode = None;
+    let mut options = SaveOptions::default();
+    let mut template = None;
+    let (mut region, mut feather) = (None, None);
+    let mut positional = Vec::new();
+
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        let Some(flag) = arg.strip_prefix("--") else {
+            positional.push(arg.clone());
+            continue;
+        };
+        let (flag, value) = flag_value(flag, &mut rest)?;
+
+        if flag == "seed" {
+            seed = Some(value.parse::<u64>().map_err(|_| {
+                DatabendError::invalid_parameter(
+                    "seed",
+                    format!("'{}' is not a whole number", value),
+                )
+            })?);
+            continue;
+        }
+
+        if flag == "seed-mode" {
+            seed_mode = Some(SeedMode::parse(&value).ok_or_else(|| {
+                let reason = format!("'{}' is not stable or flicker", value);
+                DatabendError::invalid_parameter("seed-mode", reason)
+            })?);
+            continue;
+        }
+
+        if flag == "background" {
+            options.background = Some(parse_background(&value)?);
+            continue;
+        }
+
+        if flag == "region" {
+            region = Some(value);
+            continue;
+        }
+
+        if flag == "feather" {
+            feather = Some(parse_feather(&value)?);
+            continue;
+        }
+
+        if flag == "template" {
+            batch::check_template(&value)?;
+            template = Some(value);
+            continue;
+        }
+
+        let spec = specs
+            .iter()
+            .find(|spec| spec.name == flag)
+            .ok_or_else(|| format!("{} has no parameter --{}", owner, flag))?;
+        let (start, end) = spec.parse_range(&value)?;
+        values.set(spec.name, start);
+        if value.contains("..") {
+            ends.push((spec.name, end));
+        }
+    }
+
+    options.region = parse_region(region, feather)?;
+
+    let end_values = (!ends.is_empty()).then(|| {
+        let mut end_values = values.clone();
+        for (name, end) in ends {
+            end_values.set(name, end);
+        }
+        end_values
+    });
+
+    let output_path = positional
+        .pop()
+        .filter(|_| !positional.is_empty())
+        .ok_or("expected an input and an output path")?;
+
+    Ok(RunArgs {
+        values,
+        end_values,
+        seed,
+        seed_mode,
+        options,
+        template,
+        input_paths: positional,
+        output_path,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GenericImageView, RgbaImage};
+
+    use super::*;
+    use crate::libdatabend::keylog::KeyStroke;
+    use crate::libdatabend::synestesia;
+    use crate::libdatabend::testing::TempDir;
+
+    fn replay_args(log_path: &str, input_path: Option<&str>, output_path: &str) -> ReplayArgs {
+        ReplayArgs {
+            log_path: log_path.to_string(),
+            input_path: input_path.map(str::to_string),
+            size: None,
+            background: None,
+            region: None,
+            output_path: output_path.to_string(),
+        }
+    }
+
+    /// Plays a few keys on a 40x30 image in `dir` and returns the key log
+    /// with the result.
+    fn record_session(dir: &TempDir) -> (KeyLog, RgbaImage) {
+        let recorded = dir.image("recorded.png", 40, 30);
+        let specs = libdatabend::session_params(synestesia::ID).unwrap();
+        let params = ParamValues::defaults(specs);
+        let mut session = libdatabend::start_session(synestesia::ID, &recorded, Some(7), &params)
+            .unwrap()
+            .unwrap();
+        let mut log = KeyLog::new(synestesia::ID, specs, &params, &recorded, session.as_ref());
+        for c in "glitch".chars() {
+            session.process_key(c);
+            log.push(KeyStroke::Key(c));
+        }
+        session.undo();
+        log.push(KeyStroke::Undo);
+        let image = session.canvas().image().unwrap().to_rgba8();
+        (log, image)
+    }
+
+    #[test]
+    fn replay_rebuilds_the_session_output() {
+        let dir = TempDir::new("cli-replay");
+        let (log, expected) = record_session(&dir);
+        let (log_path, output) = (dir.join("keys.log"), dir.join("out.png"));
+        log.save(&log_path).unwrap();
+
+        replay(&replay_args(&log_path, None, &output)).unwrap();
+        assert_eq!(image::open(&output).unwrap().to_rgba8(), expected);
+    }
+
+    #[test]
+    fn replay_scales_another_image_to_the_logged_size() {
+        let dir = TempDir::new("cli-replay-other");
+        let (log, _) = record_session(&dir);
+        let (log_path, output) = (dir.join("keys.log"), dir.join("out.png"));
+        log.save(&log_path).unwrap();
+        let other = dir.image("other.png", 16, 12);
+
+        replay(&replay_args(&log_path, Some(&other), &output)).unwrap();
+        let replayed = image::open(&output).unwrap().to_rgba8();
+        assert_eq!(replayed.dimensions(), (40, 30));
+
+        let parsed = KeyLog::load(&log_path, libdatabend::session_params).unwrap();
+        let mut state =
+            libdatabend::start_session(synestesia::ID, &other, Some(parsed.seed), &parsed.params)
+                .unwrap()
+                .unwrap();
+        state.canvas_mut().resize(40, 30).unwrap();
+        parsed.replay(state.as_mut());
+        assert_eq!(replayed, state.canvas().image().unwrap().to_rgba8());
+
+        let mut args = replay_args(&log_path, Some(&other), &output);
+        args.size = Some(ReplaySize::Percent(50));
+        replay(&args).unwrap();
+        assert_eq!(image::open(&output).unwrap().dimensions(), (20, 15));
+    }
+
+    #[test]
+    fn replay_rejects_a_log_whose_size_disagrees_with_its_image() {
+        let dir = TempDir::new("cli-replay-mismatch");
+        let (mut log, _) = record_session(&dir);
+        log.width = 10;
+        log.height = 10;
+        let (log_path, output) = (dir.join("keys.log"), dir.join("out.png"));
+        log.save(&log_path).unwrap();
+
+        assert!(replay(&replay_args(&log_path, None, &output)).is_err());
+        let mut args = replay_args(&log_path, None, &output);
+        args.size = Some(ReplaySize::Pixels(40, 30));
+        replay(&args).unwrap();
+    }
+
+    fn parse_words(line: &str) -> Result<Command, ArgError> {
+        let args: Vec<String> = line.split_whitespace().map(str::to_string).collect();
+        parse(&args)
+    }
+
+    #[test]
+    fn malformed_lines_are_usage_errors() {
+        for line in [
+            "frobnicate",
+            "apply",
+            "apply nosucheffect in.png out.png",
+            "apply oversensibility --shutter 3 in.png out.png",
+            "apply oversensibility --iso",
+            "apply oversensibility in.png",
+            "apply oversensibility --feather 4 in.png out.png",
+            "sequence --recipe",
+            "replay --size 10x10 log.txt",
+        ] {
+            assert!(
+                matches!(parse_words(line), Err(ArgError::Usage(_))),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn unusable_values_are_invalid_not_usage() {
+        for line in [
+            "apply oversensibility --iso 99999 in.png out.png",
+            "apply oversensibility --iso lots in.png out.png",
+            "apply oversensibility --seed -1 in.png out.png",
+            "apply oversensibility --background teal in.png out.png",
+            "apply oversensibility --region 10x10+5 in.png out.png",
+            "apply oversensibility --region 10x10+0+0 --feather wide in.png out.png",
+            "batch apply oversensibility --template {nope} in out",
+            "sequence apply oversensibility --seed-mode wobbly f%04d.png out",
+            "replay --size huge log.txt out.png",
+        ] {
+            assert!(
+                matches!(parse_words(line), Err(ArgError::Invalid(_))),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn recipe_errors_name_the_file() {
+        let dir = TempDir::new("cli-recipe");
+        let missing = dir.join("missing.recipe");
+        let Err(ArgError::Invalid(error)) = parse_words(&format!("--recipe {} in out", missing))
+        else {
+            panic!("a missing recipe should be invalid");
+        };
+        assert!(error.to_string().contains(&missing), "{}", error);
+
+        let recipe = dir.join("bad.recipe");
+        std::fs::write(&recipe, "oversensibility iso=99999\n").unwrap();
+        let Err(ArgError::Invalid(error)) = parse_words(&format!("--recipe {} in out", recipe))
+        else {
+            panic!("a recipe with a bad value should be invalid");
+        };
+        assert!(
+            matches!(error, DatabendError::InvalidRecipe(_)),
+            "{}",
+            error
+        );
+        assert!(error.to_string().contains(&recipe), "{}", error);
+    }
+}
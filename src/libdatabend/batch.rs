@@ -0,0 +1,324 @@
+use std::{
+    collections::HashSet,
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::error::DatabendError;
+use super::imageio::is_decodable;
+use super::progress::Progress;
+
+/// Output file names used when no template is given.
+pub const DEFAULT_TEMPLATE: &str = "{stem}_bent.{ext}";
+
+/// What happened to one input of a batch.
+#[derive(Debug)]
+pub struct Outcome {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    /// The seed used, or why this image failed.
+    pub result: Result<u64, DatabendError>,
+}
+
+/// Every image a batch got to, in input order.
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    pub outcomes: Vec<Outcome>,
+    /// The batch was stopped before it reached every input.
+    pub cancelled: bool,
+}
+
+impl BatchReport {
+    pub fn succeeded(&self) -> usize {
+        self.outcomes
+            .iter()
+            .filter(|outcome| outcome.result.is_ok())
+            .count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.outcomes.len() - self.succeeded()
+    }
+
+    /// The output of the last image that went through.
+    pub fn last_output(&self) -> Option<&Path> {
+        self.outcomes
+            .iter()
+            .rev()
+            .find(|outcome| outcome.result.is_ok())
+            .map(|outcome| outcome.output.as_path())
+    }
+}
+
+/// Whether `input` names several files: a directory or a wildcard pattern.
+pub fn is_batch_input(input: &str) -> bool {
+    has_wildcards(input) || Path::new(input).is_dir()
+}
+
+/// The images `input` stands for, sorted by path.
+///
+/// A directory yields its decodable images; a pattern such as `shots/*.jpg`
+/// matches `*` and `?` against file names in one directory. Hidden files are
+/// skipped unless the pattern itself starts with a dot.
+pub fn expand(input: &str) -> Result<Vec<PathBuf>, DatabendError> {
+    let path = Path::new(input);
+    let (dir, pattern) = if has_wildcards(input) {
+        let pattern = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+        if dir.is_some_and(|dir| has_wildcards(&dir.to_string_lossy())) {
+            return Err(DatabendError::InvalidPath(format!(
+                "'{}': wildcards only work in the file name",
+                input
+            )));
+        }
+        let dir = dir.unwrap_or(Path::new("."));
+        if !dir.is_dir() {
+            return Err(DatabendError::InvalidPath(format!(
+                "'{}' does not exist",
+                dir.display()
+            )));
+        }
+        (dir, Some(pattern))
+    } else if path.is_dir() {
+        (path, None)
+    } else if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    } else {
+        return Err(DatabendError::InvalidPath(format!(
+            "'{}' does not exist",
+            input
+        )));
+    };
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let shown = match &pattern {
+            Some(pattern) => {
+                wildcard_match(pattern, &name)
+                    && (!name.starts_with('.') || pattern.starts_with('.'))
+            }
+            None => !name.starts_with('.'),
+        };
+        if shown && path.is_file() && is_decodable(&path) {
+            files.push(path);
+        }
+    }
+    if files.is_empty() {
+        return Err(DatabendError::InvalidPath(format!(
+            "no images found in '{}'",
+            input
+        )));
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Checks that `template` only uses known placeholders and gives every input
+/// its own name.
+pub fn check_template(template: &str) -> Result<(), DatabendError> {
+    output_name(template, Path::new("x.png")).map(|_| ())
+}
+
+/// Fills `{stem}`, `{ext}` and `{name}` in `template` from `input`.
+pub fn output_name(template: &str, input: &Path) -> Result<String, DatabendError> {
+    let invalid = |reason: String| DatabendError::invalid_parameter("template", reason);
+    if !template.contains("{stem}") && !template.contains("{name}") {
+        return Err(invalid(
+            "needs {stem} or {name} so every image gets its own file".to_string(),
+        ));
+    }
+    if template.contains(['/', '\\']) {
+        return Err(invalid("is a file name, not a path".to_string()));
+    }
+
+    let lossy = |part: Option<&OsStr>| {
+        part.map_or(String::new(), |part| part.to_string_lossy().into_owned())
+    };
+    let mut name = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        name.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| invalid("has an unclosed '{'".to_string()))?;
+        match &rest[start + 1..start + end] {
+            "stem" => name.push_str(&lossy(input.file_stem())),
+            "ext" => name.push_str(&lossy(input.extension())),
+            "name" => name.push_str(&lossy(input.file_name())),
+            other => return Err(invalid(format!("unknown placeholder {{{}}}", other))),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    name.push_str(rest);
+    Ok(name)
+}
+
+/// Runs `apply` from every input to its templated name in `output_dir`.
+///
+/// A failing image is recorded and the batch moves on. Each image fills its
+/// own share of `progress`; cancelling stops before the next image and keeps
+/// what was already written.
+pub fn run(
+    inputs: &[PathBuf],
+    output_dir: &Path,
+    template: &str,
+    progress: &Progress,
+    mut apply: impl FnMut(&Path, &Path, &Progress) -> Result<u64, DatabendError>,
+) -> Result<BatchReport, DatabendError> {
+    check_template(template)?;
+    fs::create_dir_all(output_dir)?;
+
+    let mut report = BatchReport::default();
+    let mut written = HashSet::new();
+    for (index, input) in inputs.iter().enumerate() {
+        if progress.check().is_err() {
+            report.cancelled = true;
+            break;
+        }
+        let output = output_dir.join(output_name(template, input)?);
+        let result = if same_file(input, &output) {
+            Err(DatabendError::InvalidPath(
+                "the output would overwrite the input".to_string(),
+            ))
+        } else if !written.insert(output.clone()) {
+            Err(DatabendError::InvalidPath(format!(
+                "another image was already written to '{}'",
+                output.display()
+            )))
+        } else {
+            apply(input, &output, &progress.part(index, inputs.len()))
+        };
+        if let Err(DatabendError::Cancelled) = result {
+            report.cancelled = true;
+            break;
+        }
+        report.outcomes.push(Outcome {
+            input: input.clone(),
+            output,
+            result,
+        });
+    }
+    Ok(report)
+}
+
+fn has_wildcards(input: &str) -> bool {
+    input.contains(['*', '?'])
+}
+
+/// Whether both paths exist and lead to the same file.
+pub(super) fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Matches `name` against a pattern where `*` stands for any run of
+/// characters and `?` for exactly one.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Where the last `*` was, and how much of `name` it has swallowed so far
+    let mut star = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards_match_runs_and_single_characters() {
+        for (pattern, name) in [
+            ("*", "shot.png"),
+            ("*", ""),
+            ("*.png", "shot.png"),
+            ("*.png", ".png"),
+            ("shot?.jpg", "shot1.jpg"),
+            ("s*t*.png", "sunset_two.png"),
+            ("*a*a*", "banana"),
+            ("**.png", "x.png"),
+            ("fr?me_*", "frame_0001.tif"),
+            ("?", "é"),
+        ] {
+            assert!(
+                wildcard_match(pattern, name),
+                "{} should match {}",
+                pattern,
+                name
+            );
+        }
+        for (pattern, name) in [
+            ("*.png", "shot.png.bak"),
+            ("*.png", "shot.PNG"),
+            ("shot?.jpg", "shot.jpg"),
+            ("shot?.jpg", "shot12.jpg"),
+            ("?", ""),
+            ("a*b", "acbd"),
+            ("", "x"),
+        ] {
+            assert!(
+                !wildcard_match(pattern, name),
+                "{} should not match {}",
+                pattern,
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn templates_fill_their_placeholders() {
+        let input = Path::new("shots/sunset.final.jpg");
+        assert_eq!(
+            output_name("{stem}_bent.png", input).unwrap(),
+            "sunset.final_bent.png"
+        );
+        assert_eq!(output_name("{name}", input).unwrap(), "sunset.final.jpg");
+        assert_eq!(
+            output_name("{stem}.{ext}", input).unwrap(),
+            "sunset.final.jpg"
+        );
+        assert_eq!(
+            output_name("{stem}.{ext}", Path::new("raw")).unwrap(),
+            "raw."
+        );
+
+        for template in ["out.png", "{stem", "{stem}/{ext}", "{stem}{size}"] {
+            assert!(
+                matches!(
+                    check_template(template),
+                    Err(DatabendError::InvalidParameter { .. })
+                ),
+                "{:?}",
+                template
+            );
+        }
+    }
+}
@@ -0,0 +1,443 @@
+use image::DynamicImage;
+
+use super::error::DatabendError;
+use super::progress::Progress;
+use super::rng::DatabendRng;
+
+/// The kind of value a parameter holds, with the values it accepts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamKind {
+    /// A whole number in `min..=max`. `step` is how far one nudge moves it.
+    Int { min: i64, max: i64, step: i64 },
+    /// A number in `min..=max`; `max` may be infinite.
+    Float { min: f64, max: f64, step: f64 },
+    /// One of a fixed set of names, stored as its index.
+    Choice(&'static [&'static str]),
+}
+
+/// Describes a single effect parameter.
+#[derive(Debug, Clone, Copy)]
+pub struct ParamSpec {
+    pub name: &'static str,
+    pub label: &'static str,
+    pub kind: ParamKind,
+    pub default: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamValue {
+    Int(i64),
+    Float(f64),
+}
+
+impl ParamValue {
+    pub fn as_i64(self) -> i64 {
+        match self {
+            ParamValue::Int(v) => v,
+            ParamValue::Float(v) => v as i64,
+        }
+    }
+
+    pub fn as_f64(self) -> f64 {
+        match self {
+            ParamValue::Int(v) => v as f64,
+            ParamValue::Float(v) => v,
+        }
+    }
+}
+
+impl ParamSpec {
+    pub fn default_value(&self) -> ParamValue {
+        match self.kind {
+            ParamKind::Int { .. } | ParamKind::Choice(_) => ParamValue::Int(self.default as i64),
+            ParamKind::Float { .. } => ParamValue::Float(self.default),
+        }
+    }
+
+    /// Parses user input and checks it against the accepted range. Choices
+    /// accept either the option name or its index.
+    pub fn parse(&self, input: &str) -> Result<ParamValue, DatabendError> {
+        let input = input.trim();
+        let value = match self.kind {
+            ParamKind::Int { .. } => input
+                .parse()
+                .map(ParamValue::Int)
+                .map_err(|_| self.invalid(format!("'{}' is not a whole number", input)))?,
+            ParamKind::Float { .. } => input
+                .parse()
+                .map(ParamValue::Float)
+                .map_err(|_| self.invalid(format!("'{}' is not a number", input)))?,
+            ParamKind::Choice(options) => options
+                .iter()
+                .position(|option| option.eq_ignore_ascii_case(input))
+                .or_else(|| input.parse().ok())
+                .map(|i| ParamValue::Int(i as i64))
+                .ok_or_else(|| {
+                    self.invalid(format!("'{}' is not one of {}", input, self.range()))
+                })?,
+        };
+        self.check(value)
+    }
+
+    /// Parses `start..end` for a value that changes over a sequence. A plain
+    /// value is both start and end.
+    pub fn parse_range(&self, input: &str) -> Result<(ParamValue, ParamValue), DatabendError> {
+        match input.split_once("..") {
+            Some((start, end)) => Ok((self.parse(start)?, self.parse(end)?)),
+            None => self.parse(input).map(|value| (value, value)),
+        }
+    }
+
+    /// The value `t` of the way from `start` to `end`, with `t` in `0.0..=1.0`.
+    /// Whole numbers are rounded; choices switch halfway.
+    pub fn lerp(&self, start: ParamValue, end: ParamValue, t: f64) -> ParamValue {
+        let (a, b) = (start.as_f64(), end.as_f64());
+        match self.kind {
+            ParamKind::Int { .. } => ParamValue::Int((a + (b - a) * t).round() as i64),
+            ParamKind::Float { .. } => ParamValue::Float(a + (b - a) * t),
+            ParamKind::Choice(_) => {
+                if t < 0.5 {
+                    start
+                } else {
+                    end
+                }
+            }
+        }
+    }
+
+    /// Fails unless `value` is one this parameter accepts.
+    pub fn check(&self, value: ParamValue) -> Result<ParamValue, DatabendError> {
+        let accepted = match (self.kind, value) {
+            (ParamKind::Int { min, max, .. }, ParamValue::Int(v)) => (min..=max).contains(&v),
+            (ParamKind::Float { min, max, .. }, v) => (min..=max).contains(&v.as_f64()),
+            (ParamKind::Choice(options), ParamValue::Int(v)) => {
+                usize::try_from(v).is_ok_and(|i| i < options.len())
+            }
+            _ => false,
+        };
+        if accepted {
+            Ok(value)
+        } else {
+            Err(self.invalid(format!(
+                "{} is outside {}",
+                self.format_value(value),
+                self.range()
+            )))
+        }
+    }
+
+    /// The accepted values in a few characters, e.g. `0–6400`, `≥ 1` or `no/yes`.
+    pub fn range(&self) -> String {
+        match self.kind {
+            ParamKind::Int {
+                min, max: i64::MAX, ..
+            } => format!("≥ {}", min),
+            ParamKind::Int { min, max, .. } => format!("{}–{}", min, max),
+            ParamKind::Float { min, max, .. } if max.is_infinite() => format!("≥ {:?}", min),
+            ParamKind::Float { min, max, .. } => format!("{:?}–{:?}", min, max),
+            ParamKind::Choice(options) => options.join("/"),
+        }
+    }
+
+    /// The label followed by the accepted range, as shown in the editor.
+    pub fn display_label(&self) -> String {
+        format!("{} ({})", self.label, self.range())
+    }
+
+    /// Formats a value the way `parse` reads it back.
+    pub fn format_value(&self, value: ParamValue) -> String {
+        match (self.kind, value) {
+            (ParamKind::Choice(options), ParamValue::Int(i)) => usize::try_from(i)
+                .ok()
+                .and_then(|i| options.get(i))
+                .map_or_else(|| i.to_string(), |option| option.to_string()),
+            (_, ParamValue::Int(v)) => v.to_string(),
+            (_, ParamValue::Float(v)) => format!("{:?}", v),
+        }
+    }
+
+    /// Moves `input` by `delta` steps and clamps it to the range. Choices
+    /// wrap around. Input that isn't a number yet starts from the default.
+    pub fn nudge(&self, input: &str, delta: i64) -> String {
+        let input = input.trim();
+        let value = match self.kind {
+            ParamKind::Int { min, max, step } => {
+                let current = input.parse().unwrap_or(self.default as i64);
+                ParamValue::Int(
+                    current
+                        .saturating_add(step.saturating_mul(delta))
+                        .clamp(min, max),
+                )
+            }
+            ParamKind::Float { min, max, step } => {
+                let current = input
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|v| v.is_finite())
+                    .unwrap_or(self.default);
+                let moved = (current + step * delta as f64).clamp(min, max);
+                // Round off the binary noise that repeated steps pile up
+                ParamValue::Float((moved * 1e6).round() / 1e6)
+            }
+            ParamKind::Choice(options) => {
+                let current = self
+                    .parse(input)
+                    .map_or(self.default as i64, ParamValue::as_i64);
+                ParamValue::Int((current + delta).rem_euclid(options.len() as i64))
+            }
+        };
+        self.format_value(value)
+    }
+
+    fn invalid(&self, reason: String) -> DatabendError {
+        DatabendError::invalid_parameter(self.name, reason)
+    }
+}
+
+/// Parameter values for one effect run, keyed by `ParamSpec::name`.
+#[derive(Debug, Clone, Default)]
+pub struct ParamValues {
+    values: Vec<(&'static str, ParamValue)>,
+}
+
+impl ParamValues {
+    pub fn defaults(specs: &[ParamSpec]) -> Self {
+        Self {
+            values: specs.iter().map(|s| (s.name, s.default_value())).collect(),
+        }
+    }
+
+    /// Parses one input per spec, in order. Only an empty input means the
+    /// default; anything else must parse and be in range.
+    pub fn parse(specs: &[ParamSpec], inputs: &[String]) -> Result<Self, DatabendError> {
+        let mut values = Self::defaults(specs);
+        for (spec, input) in specs.iter().zip(inputs) {
+            if !input.trim().is_empty() {
+                values.set(spec.name, spec.parse(input)?);
+            }
+        }
+        Ok(values)
+    }
+
+    /// Fails if any parameter of `specs` is missing or out of range.
+    pub fn check(&self, specs: &[ParamSpec]) -> Result<(), DatabendError> {
+        for spec in specs {
+            let value = self
+                .get(spec.name)
+                .ok_or_else(|| DatabendError::invalid_parameter(spec.name, "missing"))?;
+            spec.check(value)?;
+        }
+        Ok(())
+    }
+
+    pub fn set(&mut self, name: &'static str, value: ParamValue) {
+        match self.values.iter_mut().find(|(n, _)| *n == name) {
+            Some(slot) => slot.1 = value,
+            None => self.values.push((name, value)),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<ParamValue> {
+        self.values
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| *v)
+    }
+
+    pub fn int(&self, name: &str) -> i64 {
+        self.get(name).map(ParamValue::as_i64).unwrap_or_default()
+    }
+
+    pub fn float(&self, name: &str) -> f64 {
+        self.get(name).map(ParamValue::as_f64).unwrap_or_default()
+    }
+}
+
+/// Start and end values for a run over a sequence of frames.
+#[derive(Debug, Clone)]
+pub struct ParamRanges {
+    specs: &'static [ParamSpec],
+    start: ParamValues,
+    end: ParamValues,
+}
+
+impl ParamRanges {
+    pub fn new(specs: &'static [ParamSpec], start: ParamValues, end: ParamValues) -> Self {
+        Self { specs, start, end }
+    }
+
+    /// Parses one input per spec like [`ParamValues::parse`], also accepting
+    /// `start..end`.
+    pub fn parse(specs: &'static [ParamSpec], inputs: &[String]) -> Result<Self, DatabendError> {
+        let mut start = ParamValues::defaults(specs);
+        let mut end = start.clone();
+        for (spec, input) in specs.iter().zip(inputs) {
+            if !input.trim().is_empty() {
+                let (from, to) = spec.parse_range(input)?;
+                start.set(spec.name, from);
+                end.set(spec.name, to);
+            }
+        }
+        Ok(Self { specs, start, end })
+    }
+
+    /// The values `t` of the way through, with `t` in `0.0..=1.0`.
+    pub fn at(&self, t: f64) -> ParamValues {
+        let mut values = self.start.clone();
+        for spec in self.specs {
+            if let (Some(start), Some(end)) = (self.start.get(spec.name), self.end.get(spec.name)) {
+                values.set(spec.name, spec.lerp(start, end, t));
+            }
+        }
+        values
+    }
+}
+
+/// A databending effect that works on an in-memory image.
+///
+/// All randomness must come from `rng` so that a seed reproduces the result.
+/// Effects run on a worker thread in the TUI, hence `Sync`.
+pub trait Effect: Sync {
+    /// Short lowercase identifier used on the command line.
+    fn name(&self) -> &'static str;
+
+    fn params(&self) -> &'static [ParamSpec];
+
+    fn apply(
+        &self,
+        img: &DynamicImage,
+        params: &ParamValues,
+        rng: &mut DatabendRng,
+        progress: &Progress,
+    ) -> Result<DynamicImage, DatabendError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ISO: ParamSpec = ParamSpec {
+        name: "iso",
+        label: "ISO",
+        kind: ParamKind::Int {
+            min: 0,
+            max: 6400,
+            step: 100,
+        },
+        default: 800.0,
+    };
+
+    const GAIN: ParamSpec = ParamSpec {
+        name: "gain",
+        label: "Gain",
+        kind: ParamKind::Float {
+            min: 0.1,
+            max: f64::INFINITY,
+            step: 0.1,
+        },
+        default: 1.0,
+    };
+
+    const STREAM: ParamSpec = ParamSpec {
+        name: "stream",
+        label: "Stream",
+        kind: ParamKind::Choice(&["interleaved", "channels"]),
+        default: 0.0,
+    };
+
+    fn rejects(spec: &ParamSpec, input: &str) -> bool {
+        matches!(
+            spec.parse(input),
+            Err(DatabendError::InvalidParameter { .. })
+        )
+    }
+
+    #[test]
+    fn parse_reads_each_kind_within_its_range() {
+        assert_eq!(ISO.parse(" 6400 ").unwrap(), ParamValue::Int(6400));
+        assert_eq!(ISO.parse("0").unwrap(), ParamValue::Int(0));
+        assert_eq!(GAIN.parse("2.5").unwrap(), ParamValue::Float(2.5));
+        assert_eq!(GAIN.parse("1e9").unwrap(), ParamValue::Float(1e9));
+        assert_eq!(STREAM.parse("Channels").unwrap(), ParamValue::Int(1));
+        assert_eq!(STREAM.parse("0").unwrap(), ParamValue::Int(0));
+
+        for input in ["6401", "-1", "1.5", "lots", ""] {
+            assert!(rejects(&ISO, input), "{:?}", input);
+        }
+        for input in ["0.05", "NaN", "-inf", "fast"] {
+            assert!(rejects(&GAIN, input), "{:?}", input);
+        }
+        for input in ["2", "-1", "planar"] {
+            assert!(rejects(&STREAM, input), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn parsed_values_format_back_to_themselves() {
+        for (spec, input) in [(&ISO, "3200"), (&GAIN, "0.3"), (&STREAM, "channels")] {
+            let value = spec.parse(input).unwrap();
+            assert_eq!(spec.format_value(value), input);
+            assert_eq!(spec.parse(&spec.format_value(value)).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn parse_range_splits_on_the_dots() {
+        let iso = ParamValue::Int;
+        assert_eq!(ISO.parse_range("100..3200").unwrap(), (iso(100), iso(3200)));
+        assert_eq!(ISO.parse_range("3200..100").unwrap(), (iso(3200), iso(100)));
+        assert_eq!(ISO.parse_range("400").unwrap(), (iso(400), iso(400)));
+        assert_eq!(
+            GAIN.parse_range("0.5..1.5").unwrap(),
+            (ParamValue::Float(0.5), ParamValue::Float(1.5))
+        );
+        assert_eq!(
+            STREAM.parse_range("interleaved..channels").unwrap(),
+            (ParamValue::Int(0), ParamValue::Int(1))
+        );
+
+        for input in ["100..", "..100", "100..9000", "100...200", "100..200..300"] {
+            assert!(ISO.parse_range(input).is_err(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn check_matches_the_kind_as_well_as_the_range() {
+        assert!(ISO.check(ParamValue::Int(800)).is_ok());
+        assert!(ISO.check(ParamValue::Int(i64::MAX)).is_err());
+        assert!(ISO.check(ParamValue::Float(800.0)).is_err());
+        assert!(GAIN.check(ParamValue::Int(3)).is_ok());
+        assert!(GAIN.check(ParamValue::Float(f64::NAN)).is_err());
+        assert!(STREAM.check(ParamValue::Int(1)).is_ok());
+        assert!(STREAM.check(ParamValue::Int(-1)).is_err());
+        assert!(STREAM.check(ParamValue::Float(0.0)).is_err());
+
+        let specs = [ISO, GAIN, STREAM];
+        let mut values = ParamValues::defaults(&specs);
+        assert!(values.check(&specs).is_ok());
+        values.set("iso", ParamValue::Int(7000));
+        assert!(values.check(&specs).is_err());
+        assert!(ParamValues::defaults(&specs[..2]).check(&specs).is_err());
+    }
+
+    #[test]
+    fn empty_inputs_keep_their_defaults() {
+        let specs = [ISO, GAIN, STREAM];
+        let inputs = ["".to_string(), "2".to_string(), " ".to_string()];
+        let values = ParamValues::parse(&specs, &inputs).unwrap();
+        assert_eq!(values.int("iso"), 800);
+        assert_eq!(values.float("gain"), 2.0);
+        assert_eq!(values.int("stream"), 0);
+    }
+
+    #[test]
+    fn nudge_steps_clamps_and_wraps() {
+        assert_eq!(ISO.nudge("6350", 1), "6400");
+        assert_eq!(ISO.nudge("50", -1), "0");
+        assert_eq!(ISO.nudge("", 2), "1000");
+        assert_eq!(GAIN.nudge("0.1", 2), "0.3");
+        assert_eq!(GAIN.nudge("0.2", -5), "0.1");
+        assert_eq!(STREAM.nudge("channels", 1), "interleaved");
+        assert_eq!(STREAM.nudge("interleaved", -1), "channels");
+    }
+}
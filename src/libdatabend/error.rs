@@ -0,0 +1,83 @@
+use std::{error::Error, fmt, io};
+
+use image::{
+    ImageError,
+    error::{DecodingError, ImageFormatHint},
+};
+
+/// Everything that can go wrong while loading, bending or saving an image.
+#[derive(Debug)]
+pub enum DatabendError {
+    Io(io::Error),
+    Decode(ImageError),
+    Encode(ImageError),
+    InvalidParameter {
+        name: String,
+        reason: String,
+    },
+    InvalidDimensions(String),
+    /// An input or output path that can't be used as given.
+    InvalidPath(String),
+    /// A recipe file that can't be read as written.
+    InvalidRecipe(String),
+    /// A session key log that can't be read as written.
+    InvalidKeyLog(String),
+    /// The run was stopped through its `Progress` before it finished.
+    Cancelled,
+}
+
+impl DatabendError {
+    pub fn invalid_parameter(name: &str, reason: impl Into<String>) -> Self {
+        DatabendError::InvalidParameter {
+            name: name.to_string(),
+            reason: reason.into(),
+        }
+    }
+
+    /// An encoded file whose structure could not be understood.
+    pub fn malformed(format: impl Into<ImageFormatHint>, reason: &str) -> Self {
+        DatabendError::Decode(ImageError::Decoding(DecodingError::new(
+            format.into(),
+            reason.to_string(),
+        )))
+    }
+
+    /// A raw pixel buffer that no longer matches the image's width and height.
+    pub fn buffer_mismatch() -> Self {
+        DatabendError::InvalidDimensions("pixel buffer does not match image size".to_string())
+    }
+}
+
+impl fmt::Display for DatabendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatabendError::Io(e) => write!(f, "I/O error: {}", e),
+            DatabendError::Decode(e) => write!(f, "Failed to decode image: {}", e),
+            DatabendError::Encode(e) => write!(f, "Failed to save image: {}", e),
+            DatabendError::InvalidParameter { name, reason } => {
+                write!(f, "Invalid parameter '{}': {}", name, reason)
+            }
+            DatabendError::InvalidDimensions(reason) => write!(f, "Invalid dimensions: {}", reason),
+            DatabendError::InvalidPath(reason) => write!(f, "Invalid path: {}", reason),
+            DatabendError::InvalidRecipe(reason) => write!(f, "Invalid recipe: {}", reason),
+            DatabendError::InvalidKeyLog(reason) => write!(f, "Invalid key log: {}", reason),
+            DatabendError::Cancelled => write!(f, "Cancelled"),
+        }
+    }
+}
+
+impl Error for DatabendError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DatabendError::Io(e) => Some(e),
+            DatabendError::Decode(e) | DatabendError::Encode(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for DatabendError {
+    fn from(e: io::Error) -> Self {
+        DatabendError::Io(e)
+    }
+}
@@ -0,0 +1,411 @@
+use std::{
+    any::Any,
+    cell::Cell,
+    fs,
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+};
+
+use image::{DynamicImage, error::ImageFormatHint};
+use rand::Rng;
+
+use super::effect::{ParamKind, ParamSpec, ParamValues};
+use super::error::DatabendError;
+use super::imageio::{self, SaveOptions};
+use super::progress::Progress;
+use super::rng::{self, DatabendRng};
+
+/// Corrupts an encoded image file rather than its decoded pixels.
+///
+/// All randomness must come from `rng` so that a seed reproduces the result.
+/// Long-running benders report through `progress` and stop when it is cancelled.
+pub trait FileBender: Sync {
+    /// Short lowercase identifier used on the command line.
+    fn name(&self) -> &'static str;
+
+    fn params(&self) -> &'static [ParamSpec];
+
+    fn bend(
+        &self,
+        bytes: Vec<u8>,
+        params: &ParamValues,
+        rng: &mut DatabendRng,
+        progress: &Progress,
+    ) -> Result<Vec<u8>, DatabendError>;
+
+    /// Whether the bent file is meant to decode at all. Raw modes that only
+    /// corrupt the container return false, and no decoded copy is made.
+    fn decodable(&self, _params: &ParamValues) -> bool {
+        true
+    }
+}
+
+/// Shared by every bender: also decode the result and save it as a clean PNG.
+pub const DECODE: ParamSpec = ParamSpec {
+    name: "decode",
+    label: "Also Save Decoded PNG",
+    kind: ParamKind::Choice(&["no", "yes"]),
+    default: 0.0,
+};
+
+/// Byte-level operations applied to the encoded file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ByteOp {
+    /// Replace a run of bytes with random values.
+    Overwrite,
+    /// Flip a single random bit.
+    BitFlip,
+    /// Insert a run of random bytes.
+    Insert,
+    /// Remove a run of bytes.
+    Delete,
+    /// Duplicate a run of bytes right after itself.
+    Repeat,
+}
+
+pub const OPS: &[&str] = &["overwrite", "bitflip", "insert", "delete", "repeat"];
+
+impl ByteOp {
+    pub fn from_index(index: i64) -> Self {
+        match index {
+            1 => ByteOp::BitFlip,
+            2 => ByteOp::Insert,
+            3 => ByteOp::Delete,
+            4 => ByteOp::Repeat,
+            _ => ByteOp::Overwrite,
+        }
+    }
+}
+
+/// Blind corruption of any file with random byte operations.
+pub struct ByteBender;
+
+const PARAMS: &[ParamSpec] = &[
+    ParamSpec {
+        name: "op",
+        label: "Operation",
+        kind: ParamKind::Choice(OPS),
+        default: 0.0,
+    },
+    ParamSpec {
+        name: "amount",
+        label: "Operations",
+        kind: ParamKind::Int {
+            min: 1,
+            max: 10_000,
+            step: 10,
+        },
+        default: 20.0,
+    },
+    ParamSpec {
+        name: "length",
+        label: "Run Length in bytes",
+        kind: ParamKind::Int {
+            min: 1,
+            max: 4096,
+            step: 1,
+        },
+        default: 4.0,
+    },
+    ParamSpec {
+        name: "header",
+        label: "Protected Header Bytes",
+        kind: ParamKind::Int {
+            min: 0,
+            max: 65_536,
+            step: 64,
+        },
+        default: 512.0,
+    },
+    DECODE,
+];
+
+impl FileBender for ByteBender {
+    fn name(&self) -> &'static str {
+        "bytes"
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        PARAMS
+    }
+
+    fn bend(
+        &self,
+        mut bytes: Vec<u8>,
+        params: &ParamValues,
+        rng: &mut DatabendRng,
+        progress: &Progress,
+    ) -> Result<Vec<u8>, DatabendError> {
+        bend_bytes(
+            &mut bytes,
+            ByteOp::from_index(params.int("op")),
+            params.int("amount").max(0) as usize,
+            params.int("length").max(1) as usize,
+            params.int("header").max(0) as usize,
+            rng,
+        );
+        progress.update(1, 1)?;
+        Ok(bytes)
+    }
+}
+
+/// Applies `amount` random `op`s to `bytes`, never touching the first `header` bytes.
+pub fn bend_bytes(
+    bytes: &mut Vec<u8>,
+    op: ByteOp,
+    amount: usize,
+    length: usize,
+    header: usize,
+    rng: &mut DatabendRng,
+) {
+    let length = length.max(1);
+    for _ in 0..amount {
+        if bytes.len() <= header + length {
+            return;
+        }
+        let pos = rng.random_range(header..bytes.len() - length);
+        match op {
+            ByteOp::Overwrite => rng.fill(&mut bytes[pos..pos + length]),
+            ByteOp::BitFlip => bytes[pos] ^= 1 << rng.random_range(0..8),
+            ByteOp::Insert => {
+                let run: Vec<u8> = (0..length).map(|_| rng.random()).collect();
+                bytes.splice(pos..pos, run);
+            }
+            ByteOp::Delete => {
+                bytes.drain(pos..pos + length);
+            }
+            ByteOp::Repeat => {
+                let run = bytes[pos..pos + length].to_vec();
+                bytes.splice(pos + length..pos + length, run);
+            }
+        }
+    }
+}
+
+/// Decodes bent bytes. Some decoders panic on corrupt input instead of
+/// returning an error, so a panic is reported as a decode failure, with its
+/// message, rather than printed over the TUI.
+pub fn decode(bytes: &[u8]) -> Result<DynamicImage, DatabendError> {
+    match quietly(|| image::load_from_memory(bytes)) {
+        Ok(result) => result.map_err(DatabendError::Decode),
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| payload.downcast_ref::<String>().map(String::as_str));
+            let reason = match message {
+                Some(message) => format!("decoder crashed on the corrupted data: {}", message),
+                None => "decoder crashed on the corrupted data".to_string(),
+            };
+            Err(DatabendError::malformed(ImageFormatHint::Unknown, &reason))
+        }
+    }
+}
+
+thread_local! {
+    /// Set while this thread runs `quietly`, so its panics aren't printed.
+    static QUIET: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Whether this thread is inside `decode`, where a panic is caught and
+/// reported as an error. A panic hook can check this to stay silent.
+pub fn decoding() -> bool {
+    QUIET.with(Cell::get)
+}
+
+/// Runs `f`, catching a panic and marking the thread as `decoding` meanwhile.
+fn quietly<T>(f: impl FnOnce() -> T) -> Result<T, Box<dyn Any + Send>> {
+    let was_quiet = QUIET.with(|quiet| quiet.replace(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    QUIET.with(|quiet| quiet.set(was_quiet));
+    result
+}
+
+/// Where the decoded copy of `output_path` is written: the same name with a
+/// `.png` extension, or `<stem>_decoded.png` when the output already is a PNG.
+pub fn decoded_path(output_path: &str) -> String {
+    let path = Path::new(output_path);
+    let is_png = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+    if is_png {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        path.with_file_name(format!("{}_decoded.png", stem))
+    } else {
+        path.with_extension("png")
+    }
+    .to_string_lossy()
+    .into_owned()
+}
+
+/// What a file bend wrote.
+#[derive(Debug)]
+pub struct Bent {
+    /// The seed that was used, which is a fresh random one when none was given.
+    pub seed: u64,
+    /// Why the decoded copy that `decode` asked for is missing. The bent
+    /// output is in place regardless, so this is a warning, not a failure.
+    pub copy_error: Option<DatabendError>,
+}
+
+impl Bent {
+    /// The missing decoded copy as a line for the status bar or stderr.
+    pub fn warning(&self) -> Option<String> {
+        self.copy_error
+            .as_ref()
+            .map(|error| format!("no decoded copy was saved: {}", error))
+    }
+}
+
+/// Runs `bender` over the raw bytes of `input_path` and writes them unchanged
+/// to `output_path`, optionally decoding the result into a clean PNG as well.
+/// The decoded copy is skipped when the bender reports raw, undecodable output,
+/// and a copy that fails to decode or save is reported in [`Bent::copy_error`].
+///
+/// With a region, the bent file is decoded and blended over the input
+/// instead, and `output_path` gets that image.
+pub fn main(
+    bender: &dyn FileBender,
+    input_path: &str,
+    output_path: &str,
+    params: &ParamValues,
+    seed: Option<u64>,
+    options: &SaveOptions,
+    progress: &Progress,
+) -> Result<Bent, DatabendError> {
+    params.check(bender.params())?;
+    let decodable = bender.decodable(params);
+    if options.region.is_some() && !decodable {
+        return Err(DatabendError::invalid_parameter(
+            bender.name(),
+            "a region blends the decoded result, but these settings produce raw, undecodable data",
+        ));
+    }
+    let seed = rng::resolve_seed(seed);
+    let bytes = fs::read(input_path)?;
+    let bytes = bender.bend(bytes, params, &mut rng::from_seed(seed), progress)?;
+    let decoded = if let Some(region) = &options.region {
+        let original = imageio::load(input_path)?;
+        let img = region.apply(&original, &decode(&bytes)?)?;
+        let options = SaveOptions {
+            source_color: Some(original.color()),
+            ..options.clone()
+        };
+        imageio::save_cancellable(&img, output_path, &options, progress)?;
+        // The output already is the decoded blend; the copy holds the same pixels as a PNG
+        (params.int("decode") == 1).then_some(Ok((img, options)))
+    } else {
+        // Once the bent file is in place the run is done; the decoded copy follows
+        imageio::write_atomically(output_path, progress, |partial| {
+            Ok(fs::write(partial, &bytes)?)
+        })?;
+        (params.int("decode") == 1 && decodable).then(|| {
+            decode(&bytes).map(|img| {
+                let options = SaveOptions {
+                    source_color: Some(img.color()),
+                    ..options.clone()
+                };
+                (img, options)
+            })
+        })
+    };
+
+    let copy_error = decoded
+        .map(|decoded| {
+            decoded.and_then(|(img, options)| {
+                imageio::save(&img, &decoded_path(output_path), &options)
+            })
+        })
+        .and_then(Result::err);
+    Ok(Bent { seed, copy_error })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libdatabend::{effect::ParamValue, region::Region, testing::TempDir};
+
+    #[test]
+    fn quiet_panics_are_caught_and_the_flag_restored() {
+        let result = quietly(|| -> u8 { panic!("bad huffman table") });
+        let payload = result.unwrap_err();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"bad huffman table"));
+        assert!(!decoding());
+
+        let nested = quietly(|| {
+            let inner = quietly(|| 1);
+            (inner.unwrap(), decoding())
+        });
+        assert_eq!(nested.unwrap(), (1, true));
+        assert!(!decoding());
+    }
+
+    #[test]
+    fn a_region_still_writes_the_decoded_copy() {
+        let dir = TempDir::new("filebend");
+        let input = dir.image("in.bmp", 24, 16);
+        let output = dir.join("out.bmp");
+        let mut params = ParamValues::defaults(PARAMS);
+        params.set("header", ParamValue::Int(64));
+        params.set("decode", ParamValue::Int(1));
+        let options = SaveOptions {
+            region: Some(Region::parse("0,0,50%,100%", 0).unwrap()),
+            ..SaveOptions::default()
+        };
+        let result = main(
+            &ByteBender,
+            &input,
+            &output,
+            &params,
+            Some(3),
+            &options,
+            &Progress::none(),
+        );
+        let written = imageio::load(&output).map(|img| img.to_rgb8());
+        let copy = imageio::load(&decoded_path(&output)).map(|img| img.to_rgb8());
+
+        assert!(result.unwrap().copy_error.is_none());
+        assert_eq!(copy.unwrap(), written.unwrap());
+    }
+
+    #[test]
+    fn an_undecodable_bend_still_writes_the_output_and_its_seed() {
+        let dir = TempDir::new("filebend-undecodable");
+        let input = dir.image("in.png", 24, 16);
+        let output = dir.join("out.png");
+        let mut params = ParamValues::defaults(PARAMS);
+        // Overwriting the whole file, header included, leaves nothing to decode
+        params.set("header", ParamValue::Int(0));
+        params.set("amount", ParamValue::Int(500));
+        params.set("length", ParamValue::Int(64));
+        params.set("decode", ParamValue::Int(1));
+        let options = SaveOptions::default();
+        let bent = main(
+            &ByteBender,
+            &input,
+            &output,
+            &params,
+            Some(11),
+            &options,
+            &Progress::none(),
+        )
+        .unwrap();
+
+        assert_eq!(bent.seed, 11);
+        assert!(matches!(bent.copy_error, Some(DatabendError::Decode(_))));
+        assert!(bent.warning().unwrap().starts_with("no decoded copy"));
+        assert!(Path::new(&output).is_file());
+        assert!(!Path::new(&decoded_path(&output)).exists());
+    }
+
+    #[test]
+    fn undecodable_bytes_are_decode_errors() {
+        for bytes in [&b""[..], b"not an image", b"\x89PNG\r\n\x1a\n"] {
+            assert!(
+                matches!(decode(bytes), Err(DatabendError::Decode(_))),
+                "{:?}",
+                bytes
+            );
+        }
+    }
+}
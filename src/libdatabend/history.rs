@@ -0,0 +1,218 @@
+use std::collections::VecDeque;
+
+/// Edits kept by interactive sessions before the oldest is dropped.
+pub const HISTORY_DEPTH: usize = 200;
+
+#[derive(Debug, Clone, Copy)]
+struct Change {
+    index: usize,
+    before: u8,
+    after: u8,
+}
+
+/// The bytes one interactive step wrote, in order, with their old values.
+#[derive(Debug, Default)]
+pub struct Edit {
+    changes: Vec<Change>,
+    /// Where the effect's own cursor was before and after the step, for
+    /// effects that walk through something as they bend.
+    position: Option<(usize, usize)>,
+}
+
+impl Edit {
+    /// Writes `value` to `buffer[index]`, remembering what was there.
+    pub fn set(&mut self, buffer: &mut [u8], index: usize, value: u8) {
+        self.changes.push(Change {
+            index,
+            before: buffer[index],
+            after: value,
+        });
+        buffer[index] = value;
+    }
+
+    /// Remembers that the step moved the effect's cursor from `before` to `after`.
+    pub fn set_position(&mut self, before: usize, after: usize) {
+        self.position = Some((before, after));
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// What undoing or redoing an edit did.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Step {
+    /// How many bytes the edit wrote.
+    pub bytes: usize,
+    /// Where the effect's cursor belongs now, if the edit recorded one.
+    pub position: Option<usize>,
+}
+
+/// Bounded undo/redo for a byte buffer. Only the touched bytes are stored,
+/// so memory grows with the number of edits, not with the image size.
+#[derive(Debug)]
+pub struct History {
+    undo: VecDeque<Edit>,
+    redo: Vec<Edit>,
+    depth: usize,
+}
+
+impl History {
+    pub fn new(depth: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            depth,
+        }
+    }
+
+    /// Records an edit that has already been applied. Clears the redo stack.
+    pub fn push(&mut self, edit: Edit) {
+        if edit.is_empty() {
+            return;
+        }
+        self.redo.clear();
+        if self.undo.len() == self.depth {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(edit);
+    }
+
+    /// Reverts the latest edit. The cursor goes back to where it was before it.
+    pub fn undo(&mut self, buffer: &mut [u8]) -> Option<Step> {
+        let edit = self.undo.pop_back()?;
+        for change in edit.changes.iter().rev() {
+            buffer[change.index] = change.before;
+        }
+        let step = Step {
+            bytes: edit.len(),
+            position: edit.position.map(|(before, _)| before),
+        };
+        self.redo.push(edit);
+        Some(step)
+    }
+
+    /// Re-applies the latest undone edit. The cursor goes to where it was after it.
+    pub fn redo(&mut self, buffer: &mut [u8]) -> Option<Step> {
+        let edit = self.redo.pop()?;
+        for change in &edit.changes {
+            buffer[change.index] = change.after;
+        }
+        let step = Step {
+            bytes: edit.len(),
+            position: edit.position.map(|(_, after)| after),
+        };
+        self.undo.push_back(edit);
+        Some(step)
+    }
+
+    pub fn undo_depth(&self) -> usize {
+        self.undo.len()
+    }
+
+    pub fn redo_depth(&self) -> usize {
+        self.redo.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(buffer: &mut [u8], writes: &[(usize, u8)]) -> Edit {
+        let mut edit = Edit::default();
+        for &(index, value) in writes {
+            edit.set(buffer, index, value);
+        }
+        edit
+    }
+
+    #[test]
+    fn undo_and_redo_walk_the_edits_in_order() {
+        let mut buffer = [0u8; 4];
+        let mut history = History::new(HISTORY_DEPTH);
+        let first = edit(&mut buffer, &[(0, 1), (1, 2)]);
+        history.push(first);
+        let second = edit(&mut buffer, &[(1, 3), (3, 4)]);
+        history.push(second);
+        assert_eq!(buffer, [1, 3, 0, 4]);
+
+        assert_eq!(history.undo(&mut buffer).map(|step| step.bytes), Some(2));
+        assert_eq!(buffer, [1, 2, 0, 0]);
+        assert_eq!(history.undo(&mut buffer).map(|step| step.bytes), Some(2));
+        assert_eq!(buffer, [0; 4]);
+        assert_eq!(history.undo(&mut buffer), None);
+        assert_eq!((history.undo_depth(), history.redo_depth()), (0, 2));
+
+        assert_eq!(history.redo(&mut buffer).map(|step| step.bytes), Some(2));
+        assert_eq!(buffer, [1, 2, 0, 0]);
+        assert_eq!(history.redo(&mut buffer).map(|step| step.bytes), Some(2));
+        assert_eq!(buffer, [1, 3, 0, 4]);
+        assert_eq!(history.redo(&mut buffer), None);
+    }
+
+    #[test]
+    fn a_byte_written_twice_in_one_edit_undoes_to_its_first_value() {
+        let mut buffer = [5u8; 2];
+        let mut history = History::new(HISTORY_DEPTH);
+        let twice = edit(&mut buffer, &[(0, 6), (0, 7)]);
+        history.push(twice);
+
+        history.undo(&mut buffer);
+        assert_eq!(buffer, [5, 5]);
+        history.redo(&mut buffer);
+        assert_eq!(buffer, [7, 5]);
+    }
+
+    #[test]
+    fn a_new_edit_clears_redo_but_an_empty_one_is_ignored() {
+        let mut buffer = [0u8; 2];
+        let mut history = History::new(HISTORY_DEPTH);
+        let first = edit(&mut buffer, &[(0, 1)]);
+        history.push(first);
+        history.undo(&mut buffer);
+
+        history.push(Edit::default());
+        assert_eq!((history.undo_depth(), history.redo_depth()), (0, 1));
+
+        let other = edit(&mut buffer, &[(1, 9)]);
+        history.push(other);
+        assert_eq!((history.undo_depth(), history.redo_depth()), (1, 0));
+        assert_eq!(history.redo(&mut buffer), None);
+        assert_eq!(buffer, [0, 9]);
+    }
+
+    #[test]
+    fn the_oldest_edit_is_dropped_past_the_depth() {
+        let mut buffer = [0u8; 1];
+        let mut history = History::new(2);
+        for value in 1..=3 {
+            let step = edit(&mut buffer, &[(0, value)]);
+            history.push(step);
+        }
+        assert_eq!(history.undo_depth(), 2);
+
+        while history.undo(&mut buffer).is_some() {}
+        assert_eq!(buffer, [1]);
+    }
+
+    #[test]
+    fn undo_and_redo_give_back_the_recorded_position() {
+        let mut buffer = [0u8; 2];
+        let mut history = History::new(HISTORY_DEPTH);
+        let mut moved = edit(&mut buffer, &[(0, 1)]);
+        moved.set_position(3, 7);
+        history.push(moved);
+        let plain = edit(&mut buffer, &[(1, 1)]);
+        history.push(plain);
+
+        assert_eq!(history.undo(&mut buffer).unwrap().position, None);
+        assert_eq!(history.undo(&mut buffer).unwrap().position, Some(3));
+        assert_eq!(history.redo(&mut buffer).unwrap().position, Some(7));
+    }
+}
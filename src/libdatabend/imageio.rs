@@ -0,0 +1,287 @@
+use std::{fs, io, path::Path};
+
+use image::{
+    ColorType, DynamicImage, ImageBuffer, ImageFormat, ImageReader, Rgb, Rgba32FImage, RgbaImage,
+};
+
+use super::effect::{Effect, ParamValues};
+use super::error::DatabendError;
+use super::progress::Progress;
+use super::region::Region;
+use super::rng;
+
+/// How a result is written to disk.
+#[derive(Debug, Clone, Default)]
+pub struct SaveOptions {
+    /// Colour type of the decoded input. Its bit depth is kept when the output
+    /// format can store it; `None` writes 8 bits per channel.
+    pub source_color: Option<ColorType>,
+    /// Composite transparent pixels onto this colour instead of keeping alpha.
+    pub background: Option<Rgb<u8>>,
+    /// Only let the result show inside this region, keeping the input's
+    /// pixels elsewhere.
+    pub region: Option<Region>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Depth {
+    Eight,
+    Sixteen,
+    Float,
+}
+
+pub fn load(input_path: &str) -> Result<DynamicImage, DatabendError> {
+    ImageReader::open(input_path)?
+        .decode()
+        .map_err(DatabendError::Decode)
+}
+
+/// Whether `path` has an extension the `image` crate can decode.
+pub fn is_decodable(path: &Path) -> bool {
+    ImageFormat::from_path(path).is_ok_and(|format| format.reading_enabled())
+}
+
+/// Rebuilds an image from a raw RGBA8 buffer. When `alpha` is false the alpha
+/// bytes are dropped, so bending an opaque source never makes it transparent.
+pub fn from_rgba(
+    width: u32,
+    height: u32,
+    raw: Vec<u8>,
+    alpha: bool,
+) -> Result<DynamicImage, DatabendError> {
+    let img: RgbaImage =
+        ImageBuffer::from_raw(width, height, raw).ok_or_else(DatabendError::buffer_mismatch)?;
+    let img = DynamicImage::ImageRgba8(img);
+    Ok(if alpha {
+        img
+    } else {
+        DynamicImage::ImageRgb8(img.to_rgb8())
+    })
+}
+
+pub fn save(
+    img: &DynamicImage,
+    output_path: &str,
+    options: &SaveOptions,
+) -> Result<(), DatabendError> {
+    save_cancellable(img, output_path, options, &Progress::none())
+}
+
+/// Like [`save`], but leaves `output_path` untouched when `progress` is
+/// cancelled before the encoded file is in place.
+pub fn save_cancellable(
+    img: &DynamicImage,
+    output_path: &str,
+    options: &SaveOptions,
+    progress: &Progress,
+) -> Result<(), DatabendError> {
+    let format = ImageFormat::from_path(output_path).map_err(DatabendError::Encode)?;
+
+    let flattened;
+    let img = match options.background {
+        Some(background) if img.color().has_alpha() => {
+            flattened = flatten(img, background);
+            &flattened
+        }
+        _ => img,
+    };
+    let alpha = (img.color().has_alpha() && supports_alpha(format)) || requires_alpha(format);
+
+    let source_depth = options.source_color.map_or(Depth::Eight, depth_of);
+    let out = match (output_depth(source_depth, format), alpha) {
+        (Depth::Eight, true) => DynamicImage::ImageRgba8(img.to_rgba8()),
+        (Depth::Eight, false) => DynamicImage::ImageRgb8(img.to_rgb8()),
+        (Depth::Sixteen, true) => DynamicImage::ImageRgba16(img.to_rgba16()),
+        (Depth::Sixteen, false) => DynamicImage::ImageRgb16(img.to_rgb16()),
+        (Depth::Float, true) => DynamicImage::ImageRgba32F(img.to_rgba32f()),
+        (Depth::Float, false) => DynamicImage::ImageRgb32F(img.to_rgb32f()),
+    };
+
+    write_atomically(output_path, progress, |partial| {
+        out.save_with_format(partial, format)
+            .map_err(DatabendError::Encode)
+    })
+}
+
+/// Lets `write` fill a hidden file next to `output_path` and renames it into
+/// place afterwards, so a failed or cancelled run never leaves a half-written
+/// output behind.
+pub fn write_atomically(
+    output_path: &str,
+    progress: &Progress,
+    write: impl FnOnce(&Path) -> Result<(), DatabendError>,
+) -> Result<(), DatabendError> {
+    let path = Path::new(output_path);
+    let name = path.file_name().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "output path has no file name")
+    })?;
+    let partial = path.with_file_name(format!(".{}.partial", name.to_string_lossy()));
+
+    let result = write(&partial)
+        .and_then(|()| progress.check())
+        .and_then(|()| Ok(fs::rename(&partial, path)?));
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result
+}
+
+/// Parses a `RRGGBB` hex colour, with or without a leading `#`.
+pub fn parse_color(input: &str) -> Option<Rgb<u8>> {
+    let hex = input.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some(Rgb([channel(0)?, channel(2)?, channel(4)?]))
+}
+
+/// Formats a colour the way `parse_color` reads it, as `RRGGBB`.
+pub fn format_color(Rgb([r, g, b]): Rgb<u8>) -> String {
+    format!("{:02X}{:02X}{:02X}", r, g, b)
+}
+
+/// Loads `input_path`, runs `effect` over it and saves the result to `output_path`.
+///
+/// Returns the seed that was used, which is a fresh random one when `seed` is `None`.
+pub fn apply_file(
+    effect: &dyn Effect,
+    input_path: &str,
+    output_path: &str,
+    params: &ParamValues,
+    seed: Option<u64>,
+    options: &SaveOptions,
+    progress: &Progress,
+) -> Result<u64, DatabendError> {
+    params.check(effect.params())?;
+    let seed = rng::resolve_seed(seed);
+    let img = load(input_path)?;
+    let mut out = effect.apply(&img, params, &mut rng::from_seed(seed), progress)?;
+    if let Some(region) = &options.region {
+        out = region.apply(&img, &out)?;
+    }
+    let options = SaveOptions {
+        source_color: Some(img.color()),
+        ..options.clone()
+    };
+    save_cancellable(&out, output_path, &options, progress)?;
+    Ok(seed)
+}
+
+fn flatten(img: &DynamicImage, background: Rgb<u8>) -> DynamicImage {
+    let mut rgba: Rgba32FImage = img.to_rgba32f();
+    let bg = background.0.map(|c| c as f32 / 255.0);
+    for pixel in rgba.pixels_mut() {
+        let alpha = pixel[3];
+        for (channel, bg) in pixel.0.iter_mut().take(3).zip(bg) {
+            *channel = *channel * alpha + bg * (1.0 - alpha);
+        }
+        pixel[3] = 1.0;
+    }
+    DynamicImage::ImageRgb32F(DynamicImage::ImageRgba32F(rgba).to_rgb32f())
+}
+
+fn supports_alpha(format: ImageFormat) -> bool {
+    !matches!(
+        format,
+        ImageFormat::Jpeg | ImageFormat::Pnm | ImageFormat::Hdr
+    )
+}
+
+/// Formats whose encoders (or, for ICO, decoders) only take RGBA, so even
+/// opaque results get an alpha channel.
+fn requires_alpha(format: ImageFormat) -> bool {
+    matches!(format, ImageFormat::Farbfeld | ImageFormat::Ico)
+}
+
+fn depth_of(color: ColorType) -> Depth {
+    match color {
+        ColorType::L16 | ColorType::La16 | ColorType::Rgb16 | ColorType::Rgba16 => Depth::Sixteen,
+        ColorType::Rgb32F | ColorType::Rgba32F => Depth::Float,
+        _ => Depth::Eight,
+    }
+}
+
+/// The deepest of `source` that `format` can store.
+fn output_depth(source: Depth, format: ImageFormat) -> Depth {
+    match (source, format) {
+        (_, ImageFormat::OpenExr | ImageFormat::Hdr) => Depth::Float,
+        (_, ImageFormat::Farbfeld) => Depth::Sixteen,
+        (Depth::Float | Depth::Sixteen, ImageFormat::Png | ImageFormat::Tiff) => Depth::Sixteen,
+        _ => Depth::Eight,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::GenericImageView;
+
+    use super::*;
+    use crate::libdatabend::testing::TempDir;
+
+    const SOURCES: [ColorType; 8] = [
+        ColorType::L8,
+        ColorType::Rgb8,
+        ColorType::Rgba8,
+        ColorType::La16,
+        ColorType::Rgb16,
+        ColorType::Rgba16,
+        ColorType::Rgb32F,
+        ColorType::Rgba32F,
+    ];
+
+    fn image(color: ColorType) -> DynamicImage {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(8, 6, |x, y| {
+            // GIF only keeps fully transparent pixels, so the first column is
+            image::Rgba([
+                x as u8 * 30,
+                y as u8 * 40,
+                90,
+                if x == 0 { 0 } else { 128 + x as u8 },
+            ])
+        }));
+        match color {
+            ColorType::L8 => DynamicImage::ImageLuma8(img.to_luma8()),
+            ColorType::Rgb8 => DynamicImage::ImageRgb8(img.to_rgb8()),
+            ColorType::La16 => DynamicImage::ImageLumaA16(img.to_luma_alpha16()),
+            ColorType::Rgb16 => DynamicImage::ImageRgb16(img.to_rgb16()),
+            ColorType::Rgba16 => DynamicImage::ImageRgba16(img.to_rgba16()),
+            ColorType::Rgb32F => DynamicImage::ImageRgb32F(img.to_rgb32f()),
+            ColorType::Rgba32F => DynamicImage::ImageRgba32F(img.to_rgba32f()),
+            _ => img,
+        }
+    }
+
+    #[test]
+    fn every_writable_format_saves_every_source() {
+        let dir = TempDir::new("imageio");
+        let mut failures = Vec::new();
+        for format in ImageFormat::all().filter(|format| format.writing_enabled()) {
+            let path = dir.join(&format!("out.{}", format.extensions_str()[0]));
+            let path = path.as_str();
+            for source in SOURCES {
+                let options = SaveOptions {
+                    source_color: Some(source),
+                    ..SaveOptions::default()
+                };
+                let result = save(&image(source), path, &options).and_then(|()| load(path));
+                let case = format!("{:?} from {:?}", format, source);
+                match result {
+                    Ok(img) => {
+                        assert_eq!(img.dimensions(), (8, 6), "{}", case);
+                        // Some decoders (GIF) always report alpha, so compare the pixels
+                        let transparent = img.to_rgba8().pixels().any(|pixel| pixel[3] < 255);
+                        let alpha = source.has_alpha() && supports_alpha(format);
+                        if transparent != alpha {
+                            failures.push(format!("{}: alpha {}", case, transparent));
+                        }
+                    }
+                    // The avif feature only brings the encoder; decoding needs avif-native
+                    Err(DatabendError::Decode(_)) if format == ImageFormat::Avif => {}
+                    Err(error) => failures.push(format!("{}: {}", case, error)),
+                }
+            }
+        }
+        assert!(failures.is_empty(), "{:#?}", failures);
+    }
+}
@@ -0,0 +1,145 @@
+use image::{
+    ColorType, DynamicImage, RgbaImage,
+    imageops::{self, FilterType},
+};
+
+use super::error::DatabendError;
+use super::history::{Edit, HISTORY_DEPTH, History};
+use super::imageio::{self, SaveOptions};
+use super::region::{self, Region, Weights};
+
+/// The RGBA buffer an interactive session bends, with its undo history.
+pub struct Canvas {
+    pub rawimg: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    pub source_color: ColorType,
+    pub history: History,
+    pub modifications_count: usize,
+    /// Where the session may show, with the pixels kept everywhere else.
+    region: Option<(Weights, DynamicImage)>,
+}
+
+impl Canvas {
+    pub fn load(input_path: &str) -> Result<Self, DatabendError> {
+        let img = imageio::load(input_path)?;
+        Ok(Self {
+            rawimg: img.to_rgba8().into_raw(),
+            width: img.width(),
+            height: img.height(),
+            source_color: img.color(),
+            history: History::new(HISTORY_DEPTH),
+            modifications_count: 0,
+            region: None,
+        })
+    }
+
+    /// Scales the buffer to `width` x `height` and forgets its history, so a
+    /// replayed session can run at another resolution.
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), DatabendError> {
+        if width == 0 || height == 0 {
+            return Err(DatabendError::InvalidDimensions(format!(
+                "{}x{} is empty",
+                width, height
+            )));
+        }
+        let img = RgbaImage::from_raw(self.width, self.height, std::mem::take(&mut self.rawimg))
+            .ok_or_else(DatabendError::buffer_mismatch)?;
+        self.rawimg = imageops::resize(&img, width, height, FilterType::Lanczos3).into_raw();
+        self.width = width;
+        self.height = height;
+        self.history = History::new(HISTORY_DEPTH);
+        self.modifications_count = 0;
+        self.region = None;
+        Ok(())
+    }
+
+    /// Limits the session to `region` of the buffer as it is now, so set it
+    /// after any resize.
+    pub fn set_region(&mut self, region: &Region) -> Result<(), DatabendError> {
+        let original = imageio::from_rgba(
+            self.width,
+            self.height,
+            self.rawimg.clone(),
+            self.source_color.has_alpha(),
+        )?;
+        self.region = Some((region.weights(self.width, self.height), original));
+        Ok(())
+    }
+
+    /// Records an edit that was just applied to `rawimg`.
+    pub fn commit(&mut self, edit: Edit) {
+        self.modifications_count += edit.len();
+        self.history.push(edit);
+    }
+
+    /// The current state of the bent buffer as an image, kept to the region
+    /// if there is one.
+    pub fn image(&self) -> Result<DynamicImage, DatabendError> {
+        let bent = imageio::from_rgba(
+            self.width,
+            self.height,
+            self.rawimg.clone(),
+            self.source_color.has_alpha(),
+        )?;
+        match &self.region {
+            Some((weights, original)) => region::blend(original, &bent, weights),
+            None => Ok(bent),
+        }
+    }
+}
+
+/// An effect played one keypress at a time by the TUI event loop.
+pub trait InteractiveState {
+    fn canvas(&self) -> &Canvas;
+
+    fn canvas_mut(&mut self) -> &mut Canvas;
+
+    fn seed(&self) -> u64;
+
+    /// Bends the buffer for one keypress and returns a status line.
+    fn process_key(&mut self, c: char) -> String;
+
+    /// Status line shown once the result has been saved.
+    fn finish_message(&self) -> String;
+
+    /// Moves the effect's own cursor to where an undone or redone edit left
+    /// it. Only effects that record a position in their edits need this.
+    fn set_position(&mut self, _position: usize) {}
+
+    fn undo(&mut self) -> String {
+        let canvas = self.canvas_mut();
+        let Some(step) = canvas.history.undo(&mut canvas.rawimg) else {
+            return "Nothing to undo".to_string();
+        };
+        canvas.modifications_count -= step.bytes;
+        let total = canvas.modifications_count;
+        if let Some(position) = step.position {
+            self.set_position(position);
+        }
+        format!("↩️ Undid {} pixel changes (Total: {})", step.bytes, total)
+    }
+
+    fn redo(&mut self) -> String {
+        let canvas = self.canvas_mut();
+        let Some(step) = canvas.history.redo(&mut canvas.rawimg) else {
+            return "Nothing to redo".to_string();
+        };
+        canvas.modifications_count += step.bytes;
+        let total = canvas.modifications_count;
+        if let Some(position) = step.position {
+            self.set_position(position);
+        }
+        format!("↪️ Redid {} pixel changes (Total: {})", step.bytes, total)
+    }
+
+    fn save(&self, output_path: &str, options: &SaveOptions) -> Result<String, DatabendError> {
+        let canvas = self.canvas();
+        let options = SaveOptions {
+            source_color: Some(canvas.source_color),
+            ..options.clone()
+        };
+        imageio::save(&canvas.image()?, output_path, &options)?;
+        Ok(self.finish_message())
+    }
+}
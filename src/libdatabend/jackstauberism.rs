@@ -1,20 +1,14 @@
-use crossterm::event::{self, Event, KeyCode};
-use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
-use image::{ImageBuffer, ImageReader, RgbImage, DynamicImage};
-use rand::{rng, Rng};
-use std::time::Duration;
-use std::error::Error;
-
-pub fn main(input_path: &str, output_path: &str) -> Result<(), Box<dyn Error>> {
-    let original_img = ImageReader::open(input_path)?
-        .decode()?
-        .to_rgba8();
-    let (width, height) = original_img.dimensions();
-    let mut img = original_img.into_raw();
-
-    enable_raw_mode()?;
-
-    let lyrics = r#"
+use rand::Rng;
+
+use super::error::DatabendError;
+use super::history::Edit;
+use super::interactive::{Canvas, InteractiveState};
+use super::rng::{self, DatabendRng};
+
+/// The name recipes, key logs and the command line use for this effect.
+pub const ID: &str = "jackstauberism";
+
+const LYRICS: &str = r#"
     Goodnight, little eye
     The moon, the sun descending
     Can I run a lie?
@@ -56,33 +50,110 @@ pub fn main(input_path: &str, output_path: &str) -> Result<(), Box<dyn Error>> {
     Gotta, gotta
     Gotta, gotta
     "#;
-    let mut lyric_index = 0;
-    let mut rng = rng();
-    let lyrics_bytes = lyrics.as_bytes();
-
-    loop {
-        if event::poll(Duration::from_millis(500))?
-            && let Ok(Event::Key(key_event)) = event::read()
-        {
-            match key_event.code {
-                KeyCode::Char(_) => {
-                    for _ in 0..img.len() / 128 {
-                        let idx = rng.random_range(0..img.len());
-                        img[idx] = lyrics_bytes[lyric_index];
-                        lyric_index = (lyric_index + 1) % lyrics_bytes.len();
-                    }
-                }
-                KeyCode::Esc => break,
-                _ => {}
-            }
+
+/// Writes the lyrics of "Databend", byte by byte, into random positions of
+/// the buffer; every keypress sings the next 1/128th of the image.
+pub struct JackStauberismState {
+    pub canvas: Canvas,
+    pub rng: DatabendRng,
+    pub seed: u64,
+    pub lyric_index: usize,
+}
+
+impl JackStauberismState {
+    pub fn new(input_path: &str, seed: Option<u64>) -> Result<Self, DatabendError> {
+        let seed = rng::resolve_seed(seed);
+        Ok(Self {
+            canvas: Canvas::load(input_path)?,
+            rng: rng::from_seed(seed),
+            seed,
+            lyric_index: 0,
+        })
+    }
+
+    /// The lyric line the next byte will come from.
+    fn current_line(&self) -> &'static str {
+        let start = LYRICS[..self.lyric_index].rfind('\n').map_or(0, |i| i + 1);
+        let end = LYRICS[self.lyric_index..]
+            .find('\n')
+            .map_or(LYRICS.len(), |i| self.lyric_index + i);
+        LYRICS[start..end].trim()
+    }
+}
+
+impl InteractiveState for JackStauberismState {
+    fn canvas(&self) -> &Canvas {
+        &self.canvas
+    }
+
+    fn canvas_mut(&mut self) -> &mut Canvas {
+        &mut self.canvas
+    }
+
+    fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn process_key(&mut self, _c: char) -> String {
+        let lyrics_bytes = LYRICS.as_bytes();
+        let rawimg = &mut self.canvas.rawimg;
+        let mut edit = Edit::default();
+        let start = self.lyric_index;
+
+        for _ in 0..rawimg.len() / 128 {
+            let idx = self.rng.random_range(0..rawimg.len());
+            edit.set(rawimg, idx, lyrics_bytes[self.lyric_index]);
+            self.lyric_index = (self.lyric_index + 1) % lyrics_bytes.len();
         }
+
+        edit.set_position(start, self.lyric_index);
+        let count = edit.len();
+        self.canvas.commit(edit);
+        format!(
+            "🎵 \"{}\" - {} bytes of lyrics written! (Total: {})",
+            self.current_line(),
+            count,
+            self.canvas.modifications_count
+        )
     }
 
-    disable_raw_mode()?;
+    fn set_position(&mut self, position: usize) {
+        self.lyric_index = position;
+    }
 
-    let new_img: RgbImage = ImageBuffer::from_raw(width, height, img)
-        .expect("Failed to create new image");
-    DynamicImage::ImageRgb8(new_img).save(output_path)?;
+    fn finish_message(&self) -> String {
+        format!(
+            "🎵 I begin to databend... {} bytes of lyrics written (seed {})",
+            self.canvas.modifications_count, self.seed
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libdatabend::testing::TempDir;
+
+    #[test]
+    fn undo_and_redo_move_the_lyrics_back_and_forth() {
+        let dir = TempDir::new("jackstauberism");
+        let mut state = JackStauberismState::new(&dir.image("in.png", 32, 32), Some(9)).unwrap();
 
-    Ok(())
+        state.process_key('a');
+        let first = state.lyric_index;
+        state.process_key('b');
+        let second = state.lyric_index;
+        assert_ne!(first, second);
+
+        state.undo();
+        assert_eq!(state.lyric_index, first);
+        state.undo();
+        assert_eq!(state.lyric_index, 0);
+        state.redo();
+        assert_eq!(state.lyric_index, first);
+
+        // A key after an undo sings the lines the undone key had sung
+        state.process_key('c');
+        assert_eq!(state.lyric_index, second);
+    }
 }
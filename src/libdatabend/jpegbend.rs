@@ -0,0 +1,324 @@
+use std::ops::Range;
+
+use image::ImageFormat;
+use rand::Rng;
+
+use super::effect::{ParamKind, ParamSpec, ParamValues};
+use super::error::DatabendError;
+use super::filebend::{DECODE, FileBender, decode};
+use super::progress::Progress;
+use super::rng::DatabendRng;
+
+const SOI: u8 = 0xD8;
+const EOI: u8 = 0xD9;
+const SOS: u8 = 0xDA;
+const TEM: u8 = 0x01;
+const RST0: u8 = 0xD0;
+const RST7: u8 = 0xD7;
+
+/// Single edits that may break decoding before the bender gives up on the
+/// rest of `amount`.
+const MAX_FAILURES: usize = 200;
+
+/// Corrupts only the entropy-coded scan data of a JPEG, leaving SOI, DQT,
+/// DHT, SOF, SOS, RST and EOI untouched. Edits that would stop the file
+/// from decoding are rolled back.
+pub struct JpegBender;
+
+const PARAMS: &[ParamSpec] = &[
+    ParamSpec {
+        name: "amount",
+        label: "Bytes to Corrupt",
+        kind: ParamKind::Int {
+            min: 1,
+            max: 10_000,
+            step: 10,
+        },
+        default: 30.0,
+    },
+    ParamSpec {
+        name: "op",
+        label: "Operation",
+        kind: ParamKind::Choice(&["overwrite", "bitflip"]),
+        default: 0.0,
+    },
+    ParamSpec {
+        name: "scan",
+        label: "Scan to Corrupt, 0 = all",
+        kind: ParamKind::Int {
+            min: 0,
+            max: i64::MAX,
+            step: 1,
+        },
+        default: 0.0,
+    },
+    DECODE,
+];
+
+impl FileBender for JpegBender {
+    fn name(&self) -> &'static str {
+        "jpeg"
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        PARAMS
+    }
+
+    fn bend(
+        &self,
+        mut bytes: Vec<u8>,
+        params: &ParamValues,
+        rng: &mut DatabendRng,
+        progress: &Progress,
+    ) -> Result<Vec<u8>, DatabendError> {
+        let scans = scan_ranges(&bytes)?;
+        let scans: Vec<Range<usize>> = match params.int("scan") {
+            0 => scans,
+            n => {
+                let index = usize::try_from(n).ok().filter(|&n| n >= 1);
+                let scan = index
+                    .and_then(|n| scans.get(n - 1))
+                    .cloned()
+                    .ok_or_else(|| {
+                        DatabendError::invalid_parameter(
+                            "scan",
+                            format!("the file only has {} scans", scans.len()),
+                        )
+                    })?;
+                vec![scan]
+            }
+        };
+
+        let candidates = corruptible_positions(&bytes, &scans);
+        if candidates.is_empty() {
+            return Ok(bytes);
+        }
+
+        let bitflip = params.int("op") == 1;
+        let amount = params.int("amount").max(0) as usize;
+        let mut remaining = amount;
+        let mut failures = 0;
+        let mut batch = remaining;
+        // Marker-safe edits can still leave a Huffman code that strict
+        // decoders reject, so each batch is checked and rolled back if the
+        // file stops decoding, retrying with smaller batches. Every attempt
+        // decodes the whole file, so cancellation is checked between them.
+        while remaining > 0 && failures < MAX_FAILURES {
+            progress.update(amount - remaining, amount)?;
+            batch = batch.min(remaining);
+            let mut bent = bytes.clone();
+            for _ in 0..batch {
+                let pos = candidates[rng.random_range(0..candidates.len())];
+                corrupt(&mut bent[pos], bitflip, rng);
+            }
+            if decode(&bent).is_ok() {
+                bytes = bent;
+                remaining -= batch;
+            } else if batch == 1 {
+                failures += 1;
+            } else {
+                batch /= 2;
+            }
+        }
+        progress.update(amount, amount)?;
+
+        Ok(bytes)
+    }
+}
+
+/// Rewrites one byte of scan data. Never produces 0xFF, which would start a
+/// new marker.
+fn corrupt(byte: &mut u8, bitflip: bool, rng: &mut DatabendRng) {
+    *byte = if bitflip {
+        let flipped = *byte ^ (1 << rng.random_range(0..8));
+        if flipped == 0xFF { *byte } else { flipped }
+    } else {
+        rng.random_range(0..0xFF)
+    };
+}
+
+/// Walks the marker structure and returns the byte range of each scan's
+/// entropy-coded data.
+pub fn scan_ranges(bytes: &[u8]) -> Result<Vec<Range<usize>>, DatabendError> {
+    let malformed = |reason: &str| DatabendError::malformed(ImageFormat::Jpeg, reason);
+
+    if bytes.len() < 4 || bytes[0] != 0xFF || bytes[1] != SOI {
+        return Err(malformed("not a JPEG file (missing SOI marker)"));
+    }
+
+    let mut scans = Vec::new();
+    let mut pos = 2;
+    loop {
+        if pos >= bytes.len() || bytes[pos] != 0xFF {
+            return Err(malformed("expected a marker"));
+        }
+        // Any number of 0xFF fill bytes may precede a marker
+        while pos < bytes.len() && bytes[pos] == 0xFF {
+            pos += 1;
+        }
+        let Some(&marker) = bytes.get(pos) else {
+            return Err(malformed("truncated marker"));
+        };
+        pos += 1;
+
+        match marker {
+            EOI => return Ok(scans),
+            SOI | TEM | RST0..=RST7 => continue,
+            _ => {}
+        }
+
+        let length = match bytes.get(pos..pos + 2) {
+            Some(&[hi, lo]) => u16::from_be_bytes([hi, lo]) as usize,
+            _ => return Err(malformed("truncated segment length")),
+        };
+        if length < 2 || pos + length > bytes.len() {
+            return Err(malformed("segment runs past the end of the file"));
+        }
+        pos += length;
+
+        if marker == SOS {
+            let start = pos;
+            pos = end_of_entropy_data(bytes, start);
+            scans.push(start..pos);
+            if pos >= bytes.len() {
+                // Missing EOI; accept what we have, as most decoders do
+                return Ok(scans);
+            }
+        }
+    }
+}
+
+/// Finds the first marker after `start` that is neither stuffing (`FF 00`)
+/// nor a restart marker.
+fn end_of_entropy_data(bytes: &[u8], start: usize) -> usize {
+    let mut pos = start;
+    while pos + 1 < bytes.len() {
+        if bytes[pos] == 0xFF {
+            match bytes[pos + 1] {
+                0x00 | RST0..=RST7 => pos += 2,
+                _ => return pos,
+            }
+        } else {
+            pos += 1;
+        }
+    }
+    bytes.len()
+}
+
+/// Positions inside `scans` that can be changed without touching a marker:
+/// neither 0xFF itself nor the byte that follows one.
+fn corruptible_positions(bytes: &[u8], scans: &[Range<usize>]) -> Vec<usize> {
+    scans
+        .iter()
+        .flat_map(|scan| scan.clone())
+        .filter(|&i| bytes[i] != 0xFF && (i == 0 || bytes[i - 1] != 0xFF))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::sync::atomic::AtomicBool;
+
+    use image::{DynamicImage, RgbImage};
+
+    use super::*;
+    use crate::libdatabend::rng;
+
+    fn jpeg(width: u32, height: u32) -> Vec<u8> {
+        let img = RgbImage::from_fn(width, height, |x, y| {
+            image::Rgb([(x * 9) as u8, (y * 5) as u8, 80])
+        });
+        let mut bytes = Vec::new();
+        DynamicImage::ImageRgb8(img)
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Jpeg)
+            .unwrap();
+        bytes
+    }
+
+    /// SOI, an APP0 after fill bytes, then two scans: the first with
+    /// stuffing and a restart marker, the second cut off before EOI when
+    /// `eoi` is false.
+    fn crafted(eoi: bool) -> Vec<u8> {
+        let mut bytes = vec![0xFF, SOI, 0xFF, 0xFF, 0xE0, 0x00, 0x04, b'J', b'F'];
+        bytes.extend([0xFF, SOS, 0x00, 0x03, 0x01]);
+        bytes.extend([0x12, 0xFF, 0x00, 0x34, 0xFF, RST0 + 3, 0x56]);
+        bytes.extend([0xFF, SOS, 0x00, 0x02]);
+        bytes.extend([0x78, 0x9A]);
+        if eoi {
+            bytes.extend([0xFF, EOI, 0xAB]);
+        }
+        bytes
+    }
+
+    #[test]
+    fn scan_ranges_cover_only_entropy_data() {
+        assert_eq!(scan_ranges(&crafted(true)).unwrap(), [14..21, 25..27]);
+        assert_eq!(scan_ranges(&crafted(false)).unwrap(), [14..21, 25..27]);
+
+        let real = jpeg(16, 16);
+        let scans = scan_ranges(&real).unwrap();
+        assert_eq!(scans.len(), 1);
+        assert_eq!(real[scans[0].end..], [0xFF, EOI]);
+    }
+
+    #[test]
+    fn malformed_marker_structure_is_rejected() {
+        let good = crafted(true);
+        let mut wrong_length = good.clone();
+        wrong_length[6] = 0x01;
+        for bytes in [
+            vec![0xFF, SOI],
+            vec![0x89, b'P', b'N', b'G'],
+            vec![0xFF, SOI, 0x00, 0x00],
+            vec![0xFF, SOI, 0xFF, 0xFF],
+            vec![0xFF, SOI, 0xFF, 0xE0, 0x00],
+            vec![0xFF, SOI, 0xFF, 0xE0, 0x00, 0x10, 0x00],
+            wrong_length,
+            good[..11].to_vec(),
+        ] {
+            assert!(
+                matches!(scan_ranges(&bytes), Err(DatabendError::Decode(_))),
+                "{:02X?}",
+                bytes
+            );
+        }
+    }
+
+    #[test]
+    fn corruptible_positions_skip_marker_bytes() {
+        let bytes = crafted(true);
+        let scans = scan_ranges(&bytes).unwrap();
+        assert_eq!(corruptible_positions(&bytes, &scans), [14, 17, 20, 25, 26]);
+    }
+
+    #[test]
+    fn bent_jpegs_decode_and_keep_their_markers() {
+        let original = jpeg(32, 24);
+        let params = ParamValues::defaults(PARAMS);
+        let bent = JpegBender
+            .bend(
+                original.clone(),
+                &params,
+                &mut rng::from_seed(5),
+                &Progress::none(),
+            )
+            .unwrap();
+        assert_ne!(bent, original);
+        assert_eq!(bent.len(), original.len());
+        let scan = scan_ranges(&original).unwrap()[0].clone();
+        assert_eq!(bent[..scan.start], original[..scan.start]);
+        assert_eq!(bent[scan.end..], original[scan.end..]);
+        assert!(decode(&bent).is_ok());
+    }
+
+    #[test]
+    fn cancelling_stops_between_decode_attempts() {
+        let cancel = AtomicBool::new(true);
+        let report = |_| {};
+        let progress = Progress::new(&report, &cancel);
+        let params = ParamValues::defaults(PARAMS);
+        let result = JpegBender.bend(jpeg(32, 24), &params, &mut rng::from_seed(5), &progress);
+        assert!(matches!(result, Err(DatabendError::Cancelled)));
+    }
+}
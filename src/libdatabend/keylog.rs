@@ -0,0 +1,503 @@
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use image::Rgb;
+
+use super::effect::{ParamKind, ParamSpec, ParamValues};
+use super::error::DatabendError;
+use super::imageio;
+use super::interactive::InteractiveState;
+use super::progress::Progress;
+use super::recipe::{self, Value};
+use super::region::Region;
+
+/// One thing done to an interactive session.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyStroke {
+    Key(char),
+    Undo,
+    Redo,
+}
+
+/// Everything needed to play an interactive session again: the effect with
+/// its seed and settings, then every key in order.
+///
+/// ```text
+/// effect = "synesthesia"
+/// seed = 42
+/// input = "/photos/in.png"
+/// size = "200x150"
+/// region = "0,0,100%,40%"
+/// spread = 500
+/// base = 50
+///
+/// [keys]
+/// 0 'h'
+/// 180 'i'
+/// 950 undo
+/// ```
+///
+/// A key may start with the milliseconds since the session began. The timing
+/// is only kept for reference: the result depends on the seed and the order
+/// of the keys alone.
+#[derive(Debug, Clone)]
+pub struct KeyLog {
+    /// The name recipes use for the effect, e.g. `synesthesia`.
+    pub effect: String,
+    pub seed: u64,
+    /// The image the session was played on.
+    pub input: String,
+    pub width: u32,
+    pub height: u32,
+    pub background: Option<Rgb<u8>>,
+    /// The part of the canvas the session was kept to.
+    pub region: Option<Region>,
+    pub specs: &'static [ParamSpec],
+    pub params: ParamValues,
+    pub strokes: Vec<(Option<Duration>, KeyStroke)>,
+    started: Instant,
+}
+
+impl KeyLog {
+    /// Starts an empty log for a session that was just set up.
+    pub fn new(
+        effect: &str,
+        specs: &'static [ParamSpec],
+        params: &ParamValues,
+        input: &str,
+        state: &dyn InteractiveState,
+    ) -> Self {
+        let canvas = state.canvas();
+        Self {
+            effect: effect.to_string(),
+            seed: state.seed(),
+            input: fs::canonicalize(input).map_or(input.to_string(), |path| {
+                path.to_string_lossy().into_owned()
+            }),
+            width: canvas.width,
+            height: canvas.height,
+            background: None,
+            region: None,
+            specs,
+            params: params.clone(),
+            strokes: Vec::new(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Adds a stroke, timed from when the log was started.
+    pub fn push(&mut self, stroke: KeyStroke) {
+        self.strokes.push((Some(self.started.elapsed()), stroke));
+    }
+
+    /// Plays every stroke into `state`, in order.
+    pub fn replay(&self, state: &mut dyn InteractiveState) {
+        for (_, stroke) in &self.strokes {
+            match stroke {
+                KeyStroke::Key(c) => state.process_key(*c),
+                KeyStroke::Undo => state.undo(),
+                KeyStroke::Redo => state.redo(),
+            };
+        }
+    }
+
+    /// Where the log goes: next to the still output, as `<stem>_keys.log`.
+    pub fn output_path(still_path: &str) -> String {
+        let still = Path::new(still_path);
+        let stem = still
+            .file_stem()
+            .map_or("output".into(), |stem| stem.to_string_lossy());
+        still
+            .with_file_name(format!("{}_keys.log", stem))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    pub fn load(
+        path: &str,
+        lookup: impl Fn(&str) -> Option<&'static [ParamSpec]>,
+    ) -> Result<Self, DatabendError> {
+        Self::parse(&fs::read_to_string(path)?, lookup)
+    }
+
+    /// Parses a log, finding the effect's parameters through `lookup`.
+    pub fn parse(
+        text: &str,
+        lookup: impl Fn(&str) -> Option<&'static [ParamSpec]>,
+    ) -> Result<Self, DatabendError> {
+        let mut header: Vec<(usize, String, Value)> = Vec::new();
+        let mut strokes = Vec::new();
+        let mut in_keys = false;
+        for (index, line) in text.lines().enumerate() {
+            let number = index + 1;
+            if in_keys {
+                // Keys are quoted characters, so `#` only starts a whole-line comment
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                strokes.push(parse_stroke(line).ok_or_else(|| {
+                    invalid(
+                        number,
+                        format!("expected [ms] 'key', undo or redo, found {}", line),
+                    )
+                })?);
+                continue;
+            }
+            let line = recipe::strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line == "[keys]" {
+                in_keys = true;
+                continue;
+            }
+            if line.starts_with('[') {
+                return Err(invalid(number, format!("unknown table {}", line)));
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid(number, "expected `key = value`".to_string()))?;
+            let key = key.trim().to_string();
+            let value = recipe::parse_value(value.trim())
+                .ok_or_else(|| invalid(number, recipe::value_error(&key, value)))?;
+            if header.iter().any(|(_, seen, _)| *seen == key) {
+                return Err(invalid(number, format!("{} is set twice", key)));
+            }
+            header.push((number, key, value));
+        }
+
+        let text_of = |name: &str| {
+            header
+                .iter()
+                .find(|(_, key, _)| key == name)
+                .map(|(line, _, value)| match value {
+                    Value::Str(text) => Ok(text.clone()),
+                    _ => Err(invalid(
+                        *line,
+                        format!("{} must be a \"quoted\" string", name),
+                    )),
+                })
+        };
+        let missing = |name: &str| DatabendError::InvalidKeyLog(format!("it has no {}", name));
+        let effect = text_of("effect").ok_or_else(|| missing("effect"))??;
+        let specs = lookup(&effect).ok_or_else(|| {
+            DatabendError::InvalidKeyLog(format!("unknown interactive effect '{}'", effect))
+        })?;
+        let input = text_of("input").ok_or_else(|| missing("input"))??;
+
+        let (mut seed, mut size, mut background) = (None, None, None);
+        let (mut region, mut feather) = (None, None);
+        let mut params = ParamValues::defaults(specs);
+        for (line, key, value) in &header {
+            match (key.as_str(), value) {
+                ("effect" | "input", _) => {}
+                ("seed", Value::Int(value)) if u64::try_from(*value).is_ok() => {
+                    seed = Some(*value as u64);
+                }
+                ("seed", _) => {
+                    return Err(invalid(*line, "seed must be a whole number".to_string()));
+                }
+                ("size", Value::Str(text)) if parse_size(text).is_some() => size = parse_size(text),
+                ("size", _) => {
+                    return Err(invalid(
+                        *line,
+                        "size must be a \"WIDTHxHEIGHT\" string".to_string(),
+                    ));
+                }
+                ("background", Value::Str(color)) if imageio::parse_color(color).is_some() => {
+                    background = imageio::parse_color(color);
+                }
+                ("background", _) => {
+                    return Err(invalid(
+                        *line,
+                        "background must be a \"RRGGBB\" colour".to_string(),
+                    ));
+                }
+                ("region", Value::Str(spec)) => region = Some((*line, spec)),
+                ("region", _) => {
+                    return Err(invalid(
+                        *line,
+                        "region must be a \"quoted\" string".to_string(),
+                    ));
+                }
+                ("feather", Value::Int(pixels)) if u32::try_from(*pixels).is_ok() => {
+                    feather = Some((*line, *pixels as u32));
+                }
+                ("feather", _) => {
+                    return Err(invalid(
+                        *line,
+                        "feather must be a whole number of pixels".to_string(),
+                    ));
+                }
+                (key, value) => {
+                    let spec = specs.iter().find(|spec| spec.name == key).ok_or_else(|| {
+                        invalid(*line, format!("{} has no parameter {}", effect, key))
+                    })?;
+                    let value = recipe::param_value(spec, value)
+                        .map_err(|reason| invalid(*line, reason))?;
+                    params.set(spec.name, value);
+                }
+            }
+        }
+        let (width, height) = size.ok_or_else(|| missing("size"))?;
+        let region = match (region, feather) {
+            (Some((line, spec)), feather) => {
+                let feather = feather.map_or(0, |(_, pixels)| pixels);
+                Some(
+                    Region::parse(spec, feather)
+                        .map_err(|error| invalid(line, error.to_string()))?,
+                )
+            }
+            (None, Some((line, _))) => {
+                return Err(invalid(line, "feather needs a region".to_string()));
+            }
+            (None, None) => None,
+        };
+        Ok(Self {
+            effect,
+            seed: seed.ok_or_else(|| missing("seed"))?,
+            input,
+            width,
+            height,
+            background,
+            region,
+            specs,
+            params,
+            strokes,
+            started: Instant::now(),
+        })
+    }
+
+    /// The log as text that `parse` reads back.
+    pub fn to_text(&self) -> String {
+        let mut out =
+            String::from("# Shutterbomb key log, play it again with `shutterbomb replay`\n");
+        out.push_str(&format!("effect = {}\n", recipe::quote(&self.effect)));
+        out.push_str(&format!("seed = {}\n", self.seed));
+        out.push_str(&format!("input = {}\n", recipe::quote(&self.input)));
+        out.push_str(&format!("size = \"{}x{}\"\n", self.width, self.height));
+        if let Some(background) = self.background {
+            let color = imageio::format_color(background);
+            out.push_str(&format!("background = {}\n", recipe::quote(&color)));
+        }
+        if let Some(region) = &self.region {
+            // A mask is kept by its full path, like the input
+            let spec = fs::canonicalize(&region.spec).map_or(region.spec.clone(), |path| {
+                path.to_string_lossy().into_owned()
+            });
+            out.push_str(&format!("region = {}\n", recipe::quote(&spec)));
+            if region.feather > 0 {
+                out.push_str(&format!("feather = {}\n", region.feather));
+            }
+        }
+        for spec in self.specs {
+            let value = self.params.get(spec.name).unwrap_or(spec.default_value());
+            let text = match spec.kind {
+                ParamKind::Choice(_) => recipe::quote(&spec.format_value(value)),
+                ParamKind::Int { .. } | ParamKind::Float { .. } => spec.format_value(value),
+            };
+            out.push_str(&format!("{} = {}\n", spec.name, text));
+        }
+
+        out.push_str("\n[keys]\n");
+        for (time, stroke) in &self.strokes {
+            if let Some(time) = time {
+                out.push_str(&format!("{} ", time.as_millis()));
+            }
+            match stroke {
+                KeyStroke::Key(c) => out.push_str(&format!("'{}'\n", c.escape_debug())),
+                KeyStroke::Undo => out.push_str("undo\n"),
+                KeyStroke::Redo => out.push_str("redo\n"),
+            }
+        }
+        out
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), DatabendError> {
+        imageio::write_atomically(path, &Progress::none(), |partial| {
+            Ok(fs::write(partial, self.to_text())?)
+        })
+    }
+}
+
+/// Reads a `WIDTHxHEIGHT` size such as `1920x1080`.
+pub fn parse_size(input: &str) -> Option<(u32, u32)> {
+    let (width, height) = input.split_once('x')?;
+    let (width, height) = (width.trim().parse().ok()?, height.trim().parse().ok()?);
+    (width > 0 && height > 0).then_some((width, height))
+}
+
+fn invalid(line: usize, reason: String) -> DatabendError {
+    DatabendError::InvalidKeyLog(format!("line {}: {}", line, reason))
+}
+
+/// Reads `[ms] 'key'`, `[ms] undo` or `[ms] redo`.
+fn parse_stroke(line: &str) -> Option<(Option<Duration>, KeyStroke)> {
+    let (time, stroke) = match line.split_once(' ') {
+        Some((ms, rest)) if ms.chars().all(|c| c.is_ascii_digit()) => {
+            (Some(Duration::from_millis(ms.parse().ok()?)), rest.trim())
+        }
+        _ => (None, line),
+    };
+    let stroke = match stroke {
+        "undo" => KeyStroke::Undo,
+        "redo" => KeyStroke::Redo,
+        _ => KeyStroke::Key(parse_char(stroke)?),
+    };
+    Some((time, stroke))
+}
+
+/// Reads a character literal as `char::escape_debug` writes it, such as
+/// `'a'`, `'\''` or `'\u{301}'`.
+fn parse_char(literal: &str) -> Option<char> {
+    let inner = literal.strip_prefix('\'')?.strip_suffix('\'')?;
+    let Some(escape) = inner.strip_prefix('\\') else {
+        let mut chars = inner.chars();
+        let c = chars.next()?;
+        return chars.next().is_none().then_some(c);
+    };
+    Some(match escape {
+        "n" => '\n',
+        "r" => '\r',
+        "t" => '\t',
+        "0" => '\0',
+        "\\" => '\\',
+        "'" => '\'',
+        "\"" => '"',
+        _ => {
+            let hex = escape.strip_prefix("u{")?.strip_suffix('}')?;
+            char::from_u32(u32::from_str_radix(hex, 16).ok()?)?
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libdatabend::synestesia;
+
+    const LOG: &str = r#"# a session
+effect = "synesthesia"
+seed = 42
+input = "/photos/in.png"
+size = "200x150"
+background = "102030"
+region = "0,0,100%,40%"
+feather = 3
+spread = 700   # the rest stay at their defaults
+
+[keys]
+0 'h'
+180 '#'
+# a comment between keys
+'\''
+950 undo
+redo
+12 '\u{301}'
+"#;
+
+    fn lookup(name: &str) -> Option<&'static [ParamSpec]> {
+        (name == "synesthesia").then_some(synestesia::PARAMS)
+    }
+
+    fn error(text: &str) -> String {
+        match KeyLog::parse(text, lookup) {
+            Err(DatabendError::InvalidKeyLog(reason)) => reason,
+            other => panic!(
+                "expected an invalid key log, got {:?}",
+                other.map(|log| log.strokes)
+            ),
+        }
+    }
+
+    #[test]
+    fn parse_reads_the_header_and_every_stroke() {
+        let log = KeyLog::parse(LOG, lookup).unwrap();
+        assert_eq!(log.effect, "synesthesia");
+        assert_eq!((log.seed, log.width, log.height), (42, 200, 150));
+        assert_eq!(log.input, "/photos/in.png");
+        assert_eq!(log.background, Some(Rgb([0x10, 0x20, 0x30])));
+        let region = log.region.as_ref().unwrap();
+        assert_eq!((region.spec.as_str(), region.feather), ("0,0,100%,40%", 3));
+        assert_eq!(
+            (log.params.int("spread"), log.params.int("base")),
+            (700, 50)
+        );
+
+        let ms = |ms| Some(Duration::from_millis(ms));
+        assert_eq!(
+            log.strokes,
+            [
+                (ms(0), KeyStroke::Key('h')),
+                (ms(180), KeyStroke::Key('#')),
+                (None, KeyStroke::Key('\'')),
+                (ms(950), KeyStroke::Undo),
+                (None, KeyStroke::Redo),
+                (ms(12), KeyStroke::Key('\u{301}')),
+            ]
+        );
+    }
+
+    #[test]
+    fn to_text_reads_back_the_same_log() {
+        let log = KeyLog::parse(LOG, lookup).unwrap();
+        let again = KeyLog::parse(&log.to_text(), lookup).unwrap();
+        assert_eq!(again.to_text(), log.to_text());
+        assert_eq!(again.strokes, log.strokes);
+        assert_eq!(again.params.int("spread"), 700);
+    }
+
+    #[test]
+    fn header_errors_name_their_line() {
+        let header = "effect = \"synesthesia\"\ninput = \"in.png\"\nsize = \"2x2\"\n";
+        let with = |extra: &str| format!("{}{}", header, extra);
+        assert!(error(&with("seed = -1\n")).starts_with("line 4: seed"));
+        assert!(error(&with("seed = 1\nseed = 2\n")).contains("seed is set twice"));
+        assert!(error(&with("seed = 1\nspread = 0\n")).starts_with("line 5:"));
+        assert!(error(&with("seed = 1\nshutter = 3\n")).contains("has no parameter shutter"));
+        assert!(error(&with("seed = 1\nfeather = 2\n")).contains("feather needs a region"));
+        assert!(error(&with("seed = 1\nregion = \"0,0,1\"\n")).starts_with("line 5:"));
+        assert!(error(&with("seed = 1\n[params]\n")).contains("unknown table"));
+        assert!(error(&with("seed = 1\nbackground = \"red\"\n")).contains("RRGGBB"));
+        assert_eq!(error(header), "it has no seed");
+        assert!(error("effect = \"pixelsort\"\n").contains("unknown interactive effect"));
+        assert!(error(&header.replace("2x2", "0x2")).contains("WIDTHxHEIGHT"));
+    }
+
+    #[test]
+    fn malformed_strokes_are_rejected() {
+        let header =
+            "effect = \"synesthesia\"\nseed = 1\ninput = \"in.png\"\nsize = \"2x2\"\n[keys]\n";
+        for stroke in [
+            "h",
+            "'ab'",
+            "''",
+            "10 'h' 'i'",
+            "'\\q'",
+            "'\\u{110000}'",
+            "-5 'h'",
+            "10",
+        ] {
+            let reason = error(&format!("{}{}\n", header, stroke));
+            assert!(
+                reason.starts_with("line 6: expected"),
+                "{:?}: {}",
+                stroke,
+                reason
+            );
+        }
+    }
+
+    #[test]
+    fn sizes_need_two_positive_sides() {
+        assert_eq!(parse_size("1920x1080"), Some((1920, 1080)));
+        assert_eq!(parse_size(" 3 x 4 "), Some((3, 4)));
+        for input in ["1920", "0x10", "10x0", "-1x10", "axb", "1x2x3"] {
+            assert_eq!(parse_size(input), None, "{:?}", input);
+        }
+    }
+}
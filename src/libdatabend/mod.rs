@@ -1,7 +1,157 @@
-pub mod oversensibility;
+pub mod batch;
+pub mod effect;
+pub mod error;
+pub mod filebend;
+pub mod history;
+pub mod imageio;
+pub mod interactive;
+pub mod jackstauberism;
+pub mod jpegbend;
+pub mod keylog;
+pub mod newnormal;
 pub mod overexposure;
+pub mod oversensibility;
+pub mod pipeline;
+pub mod pngbend;
+pub mod progress;
+pub mod recipe;
+pub mod recording;
+pub mod region;
+pub mod rng;
+pub mod sequence;
+pub mod sonification;
 pub mod synestesia;
-pub mod variationsonacloud;
 pub mod themindelectric;
-pub mod jackstauberism;
-pub mod newnormal;
\ No newline at end of file
+pub mod variationsonacloud;
+
+use effect::{Effect, ParamSpec, ParamValues};
+use error::DatabendError;
+use filebend::FileBender;
+use interactive::InteractiveState;
+
+/// Every effect that can run without user interaction.
+pub const EFFECTS: &[&dyn Effect] = &[
+    &oversensibility::Oversensibility,
+    &overexposure::Overexposure,
+    &variationsonacloud::VariationsOnACloud,
+    &themindelectric::TheMindElectric,
+    &sonification::Echo,
+    &sonification::Reverb,
+    &sonification::Bitcrush,
+    &sonification::Lowpass,
+    &sonification::Phaser,
+    &sonification::Reverse,
+];
+
+pub fn find_effect(name: &str) -> Option<&'static dyn Effect> {
+    EFFECTS
+        .iter()
+        .copied()
+        .find(|effect| effect.name().eq_ignore_ascii_case(name))
+}
+
+/// Every bender that works on the encoded file instead of decoded pixels.
+pub const BENDERS: &[&dyn FileBender] = &[
+    &filebend::ByteBender,
+    &jpegbend::JpegBender,
+    &pngbend::PngBender,
+];
+
+pub fn find_bender(name: &str) -> Option<&'static dyn FileBender> {
+    BENDERS
+        .iter()
+        .copied()
+        .find(|bender| bender.name().eq_ignore_ascii_case(name))
+}
+
+/// The params of the interactive effect that recipes and key logs call `name`.
+pub fn session_params(name: &str) -> Option<&'static [ParamSpec]> {
+    match name {
+        synestesia::ID => Some(synestesia::PARAMS),
+        jackstauberism::ID | newnormal::ID => Some(&[]),
+        _ => None,
+    }
+}
+
+/// Starts the interactive effect called `name`, or `None` if there is none.
+pub fn start_session(
+    name: &str,
+    input_path: &str,
+    seed: Option<u64>,
+    params: &ParamValues,
+) -> Option<Result<Box<dyn InteractiveState>, DatabendError>> {
+    let session: Result<Box<dyn InteractiveState>, _> = match name {
+        synestesia::ID => synestesia::SynesthesiaState::new(input_path, seed, params)
+            .map(|state| Box::new(state) as _),
+        jackstauberism::ID => jackstauberism::JackStauberismState::new(input_path, seed)
+            .map(|state| Box::new(state) as _),
+        newnormal::ID => {
+            newnormal::NewNormalState::new(input_path, seed).map(|state| Box::new(state) as _)
+        }
+        _ => return None,
+    };
+    Some(session)
+}
+
+/// Fixtures shared by the unit tests.
+#[cfg(test)]
+pub mod testing {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use image::{Rgb, RgbImage};
+
+    /// A fresh directory under the system temp dir, removed with everything
+    /// in it when dropped, so a failing test cleans up too.
+    pub struct TempDir {
+        path: PathBuf,
+    }
+
+    impl TempDir {
+        /// Tests run in parallel, so the name also gets the process id and a
+        /// counter.
+        pub fn new(name: &str) -> Self {
+            static NEXT: AtomicUsize = AtomicUsize::new(0);
+            let path = std::env::temp_dir().join(format!(
+                "shutterbomb-{}-{}-{}",
+                name,
+                std::process::id(),
+                NEXT.fetch_add(1, Ordering::Relaxed)
+            ));
+            fs::create_dir_all(&path).unwrap();
+            Self { path }
+        }
+
+        pub fn path(&self) -> &Path {
+            &self.path
+        }
+
+        /// The path of `file` in this directory, as the `&str` APIs take it.
+        pub fn join(&self, file: &str) -> String {
+            self.path.join(file).to_string_lossy().into_owned()
+        }
+
+        /// Saves a `width` x `height` [`gradient`] as `file` and returns its path.
+        pub fn image(&self, file: &str, width: u32, height: u32) -> String {
+            let path = self.join(file);
+            gradient(width, height).save(&path).unwrap();
+            path
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+
+    /// An opaque image whose every pixel differs from its neighbours.
+    pub fn gradient(width: u32, height: u32) -> RgbImage {
+        RgbImage::from_fn(width, height, |x, y| {
+            Rgb([(x * 9) as u8, (y * 7) as u8, 80])
+        })
+    }
+}
@@ -1,45 +1,65 @@
-use image::{DynamicImage, ImageBuffer, ImageReader, RgbImage};
-use rand::{Rng, rng};
-use crossterm::event::{self, Event, KeyCode};
-use crossterm::terminal::{enable_raw_mode, disable_raw_mode};
-
-pub fn main(input_path: &str, output_path: &str) {
-    let original_img = ImageReader::open(input_path)
-        .unwrap()
-        .decode()
-        .unwrap();
-    let (width, height) = (original_img.width(), original_img.height());
-    let mut img = original_img.to_rgba8().into_raw();
-
-    enable_raw_mode().expect("failed to enable raw mode");
-
-    let mut luck = rng();
-    loop {
-        if event::poll(std::time::Duration::from_millis(500)).unwrap()
-            && let Event::Key(key_event) = event::read().unwrap()
-        {
-            match key_event.code {
-                KeyCode::Char(_) => {
-                    for _ in 0..img.len() / 16 {
-                        let idx = luck.random_range(0..img.len());
-                        img[idx] = luck.random_range(0..=255);
-                    }
-                }
-                KeyCode::Esc => {
-                    println!("It's time to step out onto the new normal...");
-                    break;
-                }
-                _ => {}
-            }
-        }
+use rand::Rng;
+
+use super::error::DatabendError;
+use super::history::Edit;
+use super::interactive::{Canvas, InteractiveState};
+use super::rng::{self, DatabendRng};
+
+/// The name recipes, key logs and the command line use for this effect.
+pub const ID: &str = "newnormal";
+
+/// Randomizes 1/16th of the buffer with every keypress.
+pub struct NewNormalState {
+    pub canvas: Canvas,
+    pub luck: DatabendRng,
+    pub seed: u64,
+}
+
+impl NewNormalState {
+    pub fn new(input_path: &str, seed: Option<u64>) -> Result<Self, DatabendError> {
+        let seed = rng::resolve_seed(seed);
+        Ok(Self {
+            canvas: Canvas::load(input_path)?,
+            luck: rng::from_seed(seed),
+            seed,
+        })
     }
+}
 
-    disable_raw_mode().expect("failed to disable raw mode");
+impl InteractiveState for NewNormalState {
+    fn canvas(&self) -> &Canvas {
+        &self.canvas
+    }
 
-    let rgb_img: RgbImage = ImageBuffer::from_raw(width, height, img)
-        .expect("failed to create image buffer");
+    fn canvas_mut(&mut self) -> &mut Canvas {
+        &mut self.canvas
+    }
 
-    DynamicImage::ImageRgb8(rgb_img)
-        .save(output_path)
-        .expect("failed to save image");
-}
\ No newline at end of file
+    fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn process_key(&mut self, c: char) -> String {
+        let rawimg = &mut self.canvas.rawimg;
+        let mut edit = Edit::default();
+
+        for _ in 0..rawimg.len() / 16 {
+            let idx = self.luck.random_range(0..rawimg.len());
+            edit.set(rawimg, idx, self.luck.random_range(0..=255));
+        }
+
+        let count = edit.len();
+        self.canvas.commit(edit);
+        format!(
+            "🌈 Key '{}' pressed - {} bytes randomized! (Total: {})",
+            c, count, self.canvas.modifications_count
+        )
+    }
+
+    fn finish_message(&self) -> String {
+        format!(
+            "🌈 It's time to step out onto the new normal... {} bytes changed (seed {})",
+            self.canvas.modifications_count, self.seed
+        )
+    }
+}
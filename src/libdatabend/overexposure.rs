@@ -1,26 +1,70 @@
-use image::{DynamicImage, ImageBuffer, ImageReader, RgbaImage};
-use rand::{rng, Rng};
+use image::DynamicImage;
+use rand::Rng;
 
-pub fn main(input_path: &str, output_path: &str, exposure_factor: f32) {
-    let img = ImageReader::open(input_path)
-        .expect("Failed to open image")
-        .decode()
-        .expect("Failed to decode image");
+use super::effect::{Effect, ParamKind, ParamSpec, ParamValues};
+use super::error::DatabendError;
+use super::imageio;
+use super::progress::Progress;
+use super::rng::DatabendRng;
 
-    let mut rawimg = img.to_rgba8().into_raw();
-    let mut rng = rng();
+pub struct Overexposure;
 
-    let boost_max = (50.0 * exposure_factor).max(1.0) as u8;
-    for chunk in rawimg.chunks_mut(4) {
-        for channel in chunk.iter_mut().take(3) {
-            let boost: u8 = rng.random_range(0..boost_max);
-            let sum = *channel as u16 + boost as u16;
-            *channel = sum.min(255) as u8;
-        }
+const PARAMS: &[ParamSpec] = &[
+    ParamSpec {
+        name: "exposure",
+        label: "Exposure Factor",
+        kind: ParamKind::Float {
+            min: 0.1,
+            max: 3.0,
+            step: 0.1,
+        },
+        default: 1.5,
+    },
+    ParamSpec {
+        name: "boost",
+        label: "Brightest Boost at Factor 1.0",
+        kind: ParamKind::Float {
+            min: 1.0,
+            max: 255.0,
+            step: 5.0,
+        },
+        default: 50.0,
+    },
+];
+
+impl Effect for Overexposure {
+    fn name(&self) -> &'static str {
+        "overexposure"
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        PARAMS
     }
-    let new_img: RgbaImage = ImageBuffer::from_raw(img.width(), img.height(), rawimg)
-        .expect("Failed to create new image");
 
-    let rgb_image = DynamicImage::ImageRgba8(new_img).to_rgb8();
-    rgb_image.save(output_path).expect("Failed to save image");
+    fn apply(
+        &self,
+        img: &DynamicImage,
+        params: &ParamValues,
+        rng: &mut DatabendRng,
+        progress: &Progress,
+    ) -> Result<DynamicImage, DatabendError> {
+        let exposure_factor = params.float("exposure") as f32;
+        let boost_scale = params.float("boost") as f32;
+        let mut rawimg = img.to_rgba8().into_raw();
+
+        let boost_max = (boost_scale * exposure_factor).max(1.0) as u8;
+        let height = img.height() as usize;
+        let row_len = img.width() as usize * 4;
+        for (y, row) in rawimg.chunks_mut(row_len).enumerate() {
+            for chunk in row.chunks_mut(4) {
+                for channel in chunk.iter_mut().take(3) {
+                    let boost: u8 = rng.random_range(0..boost_max);
+                    let sum = *channel as u16 + boost as u16;
+                    *channel = sum.min(255) as u8;
+                }
+            }
+            progress.update(y + 1, height)?;
+        }
+        imageio::from_rgba(img.width(), img.height(), rawimg, img.color().has_alpha())
+    }
 }
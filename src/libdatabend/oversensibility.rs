@@ -1,25 +1,56 @@
-use image::{ImageReader, ImageBuffer, RgbaImage, DynamicImage};
-use rand::{rng, Rng};
-
-pub fn main(input_path: &str, output_path: &str, iso: &i32) {
-    let img = ImageReader::open(input_path)
-        .expect("Failed to open image")
-        .decode()
-        .expect("Failed to decode image");
-
-    let mut rawimg = img.to_rgba8().into_raw();
-    let intensity = 0.01 * *iso as f32 / 1000.0; // More reasonable intensity calculation
-    let mut rng = rng();
-
-    for byte in rawimg.iter_mut() {
-        if rng.random_bool(intensity as f64) {
-            *byte = rng.random_range(0..=255);
-        }
+use image::DynamicImage;
+use rand::Rng;
+
+use super::effect::{Effect, ParamKind, ParamSpec, ParamValues};
+use super::error::DatabendError;
+use super::imageio;
+use super::progress::Progress;
+use super::rng::DatabendRng;
+
+pub struct Oversensibility;
+
+const PARAMS: &[ParamSpec] = &[ParamSpec {
+    name: "iso",
+    label: "ISO",
+    kind: ParamKind::Int {
+        min: 0,
+        max: 6400,
+        step: 100,
+    },
+    default: 800.0,
+}];
+
+impl Effect for Oversensibility {
+    fn name(&self) -> &'static str {
+        "oversensibility"
     }
 
-    let new_img: RgbaImage = ImageBuffer::from_raw(img.width(), img.height(), rawimg)
-        .expect("Failed to create new image");
+    fn params(&self) -> &'static [ParamSpec] {
+        PARAMS
+    }
+
+    fn apply(
+        &self,
+        img: &DynamicImage,
+        params: &ParamValues,
+        rng: &mut DatabendRng,
+        progress: &Progress,
+    ) -> Result<DynamicImage, DatabendError> {
+        let iso = params.int("iso");
+        let mut rawimg = img.to_rgba8().into_raw();
+        let intensity = 0.01 * iso as f32 / 1000.0; // More reasonable intensity calculation
 
-    let rgb_image = DynamicImage::ImageRgba8(new_img).to_rgb8();
-    rgb_image.save(output_path).expect("Failed to save image");
+        let height = img.height() as usize;
+        let row_len = img.width() as usize * 4;
+        for (y, row) in rawimg.chunks_mut(row_len).enumerate() {
+            for byte in row.iter_mut() {
+                if rng.random_bool(intensity as f64) {
+                    *byte = rng.random_range(0..=255);
+                }
+            }
+            progress.update(y + 1, height)?;
+        }
+
+        imageio::from_rgba(img.width(), img.height(), rawimg, img.color().has_alpha())
+    }
 }
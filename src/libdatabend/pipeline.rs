@@ -0,0 +1,71 @@
+use image::DynamicImage;
+
+use super::effect::{Effect, ParamValues};
+use super::error::DatabendError;
+use super::imageio::{self, SaveOptions};
+use super::progress::Progress;
+use super::rng::{self, DatabendRng};
+
+pub struct PipelineStep {
+    pub effect: &'static dyn Effect,
+    pub params: ParamValues,
+    pub enabled: bool,
+}
+
+/// An ordered chain of effects that runs entirely in memory.
+#[derive(Default)]
+pub struct Pipeline {
+    pub steps: Vec<PipelineStep>,
+}
+
+impl Pipeline {
+    pub fn enabled_steps(&self) -> impl Iterator<Item = &PipelineStep> {
+        self.steps.iter().filter(|step| step.enabled)
+    }
+
+    /// Feeds `img` through every enabled step in order. All steps share `rng`,
+    /// so one seed reproduces the whole chain. Each step fills an equal share
+    /// of `progress`.
+    pub fn run(
+        &self,
+        img: DynamicImage,
+        rng: &mut DatabendRng,
+        progress: &Progress,
+    ) -> Result<DynamicImage, DatabendError> {
+        let count = self.enabled_steps().count();
+        self.enabled_steps()
+            .enumerate()
+            .try_fold(img, |img, (index, step)| {
+                step.params.check(step.effect.params())?;
+                step.effect
+                    .apply(&img, &step.params, rng, &progress.part(index, count))
+            })
+    }
+
+    /// Loads `input_path`, runs the chain and saves once to `output_path`.
+    ///
+    /// Returns the seed that was used, which is a fresh random one when `seed` is `None`.
+    pub fn apply_file(
+        &self,
+        input_path: &str,
+        output_path: &str,
+        seed: Option<u64>,
+        options: &SaveOptions,
+        progress: &Progress,
+    ) -> Result<u64, DatabendError> {
+        let seed = rng::resolve_seed(seed);
+        let img = imageio::load(input_path)?;
+        let options = SaveOptions {
+            source_color: Some(img.color()),
+            ..options.clone()
+        };
+        // The untouched input is only kept when a region needs it
+        let original = options.region.is_some().then(|| img.clone());
+        let mut out = self.run(img, &mut rng::from_seed(seed), progress)?;
+        if let (Some(region), Some(original)) = (&options.region, &original) {
+            out = region.apply(original, &out)?;
+        }
+        imageio::save_cancellable(&out, output_path, &options, progress)?;
+        Ok(seed)
+    }
+}
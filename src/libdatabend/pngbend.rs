@@ -0,0 +1,484 @@
+use std::io::{Read, Write};
+
+use flate2::{Compression, read::ZlibDecoder, write::ZlibEncoder};
+use image::ImageFormat;
+
+use super::effect::{ParamKind, ParamSpec, ParamValues};
+use super::error::DatabendError;
+use super::filebend::{self, ByteOp, DECODE, FileBender, OPS};
+use super::progress::Progress;
+use super::rng::DatabendRng;
+
+pub(super) const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// The most inflated image data a header may ask for when the scanlines are
+/// inflated. IHDR sizes come from the file, so they are checked before
+/// anything is allocated for them.
+const MAX_SCANLINE_BYTES: usize = 1 << 30;
+
+/// Adam7 passes as (x offset, y offset, x step, y step).
+const ADAM7: [(u32, u32, u32, u32); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+/// Corrupts the image data of a PNG, either the compressed IDAT stream or
+/// the inflated, filtered scanlines, and rebuilds the chunks with valid
+/// lengths and CRCs.
+pub struct PngBender;
+
+const PARAMS: &[ParamSpec] = &[
+    ParamSpec {
+        name: "target",
+        label: "Target",
+        kind: ParamKind::Choice(&["scanlines", "compressed"]),
+        default: 0.0,
+    },
+    ParamSpec {
+        name: "op",
+        label: "Operation",
+        kind: ParamKind::Choice(OPS),
+        default: 0.0,
+    },
+    ParamSpec {
+        name: "amount",
+        label: "Operations",
+        kind: ParamKind::Int {
+            min: 1,
+            max: 10_000,
+            step: 10,
+        },
+        default: 20.0,
+    },
+    ParamSpec {
+        name: "length",
+        label: "Run Length in bytes",
+        kind: ParamKind::Int {
+            min: 1,
+            max: 4096,
+            step: 1,
+        },
+        default: 4.0,
+    },
+    ParamSpec {
+        name: "redeflate",
+        label: "Re-deflate (no: raw, undecodable)",
+        kind: ParamKind::Choice(&["no", "yes"]),
+        default: 1.0,
+    },
+    DECODE,
+];
+
+impl FileBender for PngBender {
+    fn name(&self) -> &'static str {
+        "png"
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        PARAMS
+    }
+
+    fn bend(
+        &self,
+        bytes: Vec<u8>,
+        params: &ParamValues,
+        rng: &mut DatabendRng,
+        progress: &Progress,
+    ) -> Result<Vec<u8>, DatabendError> {
+        let chunks = chunks(&bytes)?;
+        let header = header(&chunks)?;
+        let mut idat: Vec<u8> = chunks
+            .iter()
+            .filter(|chunk| &chunk.kind == b"IDAT")
+            .flat_map(|chunk| chunk.data.iter().copied())
+            .collect();
+
+        let op = ByteOp::from_index(params.int("op"));
+        let amount = params.int("amount").max(0) as usize;
+        let length = params.int("length").max(1) as usize;
+
+        // Inflating, bending and deflating again are reported as thirds
+        if params.int("target") == 0 {
+            let rows = scanline_rows(&header)?;
+            let mut scanlines = inflate(&idat, rows.size);
+            rows.check_inflated(scanlines.len())?;
+            progress.update(1, 3)?;
+            filebend::bend_bytes(&mut scanlines, op, amount, length, 0, rng);
+            repair_scanlines(&mut scanlines, &rows);
+            progress.update(2, 3)?;
+            idat = deflate(&scanlines)?;
+        } else {
+            // Raw corruption never inflates anything, so any size goes
+            let rows = match params.int("redeflate") {
+                1 => Some(scanline_rows(&header)?),
+                _ => None,
+            };
+            if let Some(rows) = &rows {
+                rows.check_inflated(inflate(&idat, rows.size).len())?;
+            }
+            progress.update(1, 3)?;
+            // Keep the two-byte zlib header so the stream is still recognised
+            filebend::bend_bytes(&mut idat, op, amount, length, 2, rng);
+            progress.update(2, 3)?;
+            if let Some(rows) = rows {
+                // Inflate as far as the damage allows and pack that up again
+                let mut scanlines = inflate(&idat, rows.size);
+                repair_scanlines(&mut scanlines, &rows);
+                idat = deflate(&scanlines)?;
+            }
+        }
+        progress.update(3, 3)?;
+
+        let mut out = SIGNATURE.to_vec();
+        let mut idat_written = false;
+        for chunk in &chunks {
+            if &chunk.kind != b"IDAT" {
+                write_chunk(&mut out, &chunk.kind, chunk.data);
+            } else if !idat_written {
+                write_chunk(&mut out, b"IDAT", &idat);
+                idat_written = true;
+            }
+        }
+        Ok(out)
+    }
+
+    fn decodable(&self, params: &ParamValues) -> bool {
+        // Without re-deflating, the bent zlib stream is raw corruption that
+        // decoders almost always reject
+        params.int("target") == 0 || params.int("redeflate") == 1
+    }
+}
+
+pub(super) struct Chunk<'a> {
+    pub kind: [u8; 4],
+    pub data: &'a [u8],
+}
+
+struct Header {
+    width: u32,
+    height: u32,
+    bits_per_pixel: usize,
+    interlaced: bool,
+}
+
+/// The layout of the inflated stream: runs of equally long scanlines.
+struct Rows {
+    /// `(length, count)` for each Adam7 pass, or the whole image; the length
+    /// includes the filter byte.
+    runs: Vec<(usize, usize)>,
+    /// Total bytes, at most `MAX_SCANLINE_BYTES`.
+    size: usize,
+}
+
+impl Rows {
+    /// Rejects an input whose IDAT stream inflates to less than its header
+    /// promises. Only bent streams are padded out with zeros; a short input
+    /// is not a PNG the bender can be trusted to rebuild.
+    fn check_inflated(&self, inflated: usize) -> Result<(), DatabendError> {
+        if inflated < self.size {
+            return Err(malformed(&format!(
+                "IHDR needs {} bytes of image data, but IDAT only holds {}",
+                self.size, inflated
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn malformed(reason: &str) -> DatabendError {
+    DatabendError::malformed(ImageFormat::Png, reason)
+}
+
+/// Splits a PNG into its chunks up to and including IEND. Stored CRCs are
+/// not checked, since they are rewritten anyway.
+pub(super) fn chunks(bytes: &[u8]) -> Result<Vec<Chunk<'_>>, DatabendError> {
+    if !bytes.starts_with(SIGNATURE) {
+        return Err(malformed("not a PNG file (missing signature)"));
+    }
+
+    let mut chunks = Vec::new();
+    let mut pos = SIGNATURE.len();
+    while pos < bytes.len() {
+        let Some(&[a, b, c, d, e, f, g, h]) = bytes.get(pos..pos + 8) else {
+            return Err(malformed("truncated chunk header"));
+        };
+        let length = u32::from_be_bytes([a, b, c, d]) as usize;
+        let kind = [e, f, g, h];
+        let data = bytes
+            .get(pos + 8..pos + 8 + length)
+            .ok_or_else(|| malformed("chunk runs past the end of the file"))?;
+        chunks.push(Chunk { kind, data });
+        pos += 12 + length;
+        if &kind == b"IEND" {
+            break;
+        }
+    }
+
+    if !chunks.iter().any(|chunk| &chunk.kind == b"IDAT") {
+        return Err(malformed("no IDAT chunk"));
+    }
+    Ok(chunks)
+}
+
+fn header(chunks: &[Chunk]) -> Result<Header, DatabendError> {
+    let ihdr = chunks
+        .first()
+        .filter(|chunk| &chunk.kind == b"IHDR" && chunk.data.len() == 13)
+        .ok_or_else(|| malformed("missing IHDR chunk"))?
+        .data;
+    let channels = match ihdr[9] {
+        0 | 3 => 1,
+        4 => 2,
+        2 => 3,
+        6 => 4,
+        _ => return Err(malformed("unknown colour type")),
+    };
+    Ok(Header {
+        width: u32::from_be_bytes([ihdr[0], ihdr[1], ihdr[2], ihdr[3]]),
+        height: u32::from_be_bytes([ihdr[4], ihdr[5], ihdr[6], ihdr[7]]),
+        bits_per_pixel: channels * ihdr[8] as usize,
+        interlaced: ihdr[12] == 1,
+    })
+}
+
+/// Lengths of the scanlines in the inflated stream, checked against
+/// `MAX_SCANLINE_BYTES`.
+fn scanline_rows(header: &Header) -> Result<Rows, DatabendError> {
+    let too_large = || {
+        malformed(&format!(
+            "{}x{} at {} bits per pixel is too large",
+            header.width, header.height, header.bits_per_pixel
+        ))
+    };
+    let passes: &[_] = if header.interlaced {
+        &ADAM7
+    } else {
+        &[(0, 0, 1, 1)]
+    };
+    let mut rows = Rows {
+        runs: Vec::new(),
+        size: 0,
+    };
+    for &(x0, y0, dx, dy) in passes {
+        let width = header.width.saturating_sub(x0).div_ceil(dx) as usize;
+        let height = header.height.saturating_sub(y0).div_ceil(dy) as usize;
+        if width == 0 || height == 0 {
+            continue;
+        }
+        let row = width
+            .checked_mul(header.bits_per_pixel)
+            .map(|bits| 1 + bits.div_ceil(8))
+            .ok_or_else(too_large)?;
+        rows.size = row
+            .checked_mul(height)
+            .and_then(|bytes| rows.size.checked_add(bytes))
+            .filter(|&size| size <= MAX_SCANLINE_BYTES)
+            .ok_or_else(too_large)?;
+        rows.runs.push((row, height));
+    }
+    Ok(rows)
+}
+
+/// Inflates as much of a zlib stream as possible, up to `limit` bytes,
+/// keeping everything that came out before the first error.
+fn inflate(data: &[u8], limit: usize) -> Vec<u8> {
+    let mut decoder = ZlibDecoder::new(data).take(limit as u64);
+    let mut out = Vec::new();
+    let mut buf = [0; 32 * 1024];
+    while let Ok(n @ 1..) = decoder.read(&mut buf) {
+        out.extend_from_slice(&buf[..n]);
+    }
+    out
+}
+
+fn deflate(data: &[u8]) -> Result<Vec<u8>, DatabendError> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+/// Pads or truncates `scanlines` to the size the header expects and maps
+/// every filter byte back into the valid 0..=4 range, so decoders accept it.
+fn repair_scanlines(scanlines: &mut Vec<u8>, rows: &Rows) {
+    scanlines.resize(rows.size, 0);
+    let mut pos = 0;
+    for &(row, count) in &rows.runs {
+        for _ in 0..count {
+            scanlines[pos] %= 5;
+            pos += row;
+        }
+    }
+}
+
+/// Appends a chunk with its length and CRC.
+pub(super) fn write_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    let mut crc = crc32fast::Hasher::new();
+    crc.update(kind);
+    crc.update(data);
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    out.extend_from_slice(&crc.finalize().to_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::{DynamicImage, GenericImageView, RgbaImage};
+
+    use super::*;
+    use crate::libdatabend::effect::ParamValue;
+    use crate::libdatabend::rng;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let img = RgbaImage::from_fn(width, height, |x, y| {
+            image::Rgba([x as u8, y as u8, 7, 255])
+        });
+        let mut bytes = Vec::new();
+        DynamicImage::ImageRgba8(img)
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    /// A PNG whose IHDR claims `width` x `height` RGBA8 over a tiny IDAT.
+    fn crafted(width: u32, height: u32, interlaced: bool) -> Vec<u8> {
+        let mut ihdr = Vec::new();
+        ihdr.extend_from_slice(&width.to_be_bytes());
+        ihdr.extend_from_slice(&height.to_be_bytes());
+        ihdr.extend_from_slice(&[8, 6, 0, 0, interlaced as u8]);
+        let mut out = SIGNATURE.to_vec();
+        write_chunk(&mut out, b"IHDR", &ihdr);
+        write_chunk(&mut out, b"IDAT", &deflate(&[0; 64]).unwrap());
+        write_chunk(&mut out, b"IEND", &[]);
+        out
+    }
+
+    fn bend(bytes: Vec<u8>, target: &str) -> Result<Vec<u8>, DatabendError> {
+        let mut params = ParamValues::defaults(PARAMS);
+        params.set("target", PARAMS[0].parse(target).unwrap());
+        PngBender.bend(bytes, &params, &mut rng::from_seed(3), &Progress::none())
+    }
+
+    #[test]
+    fn chunks_get_valid_crcs() {
+        let mut out = Vec::new();
+        write_chunk(&mut out, b"IEND", &[]);
+        assert_eq!(out, b"\0\0\0\0IEND\xae\x42\x60\x82");
+    }
+
+    #[test]
+    fn bent_pngs_still_decode_at_their_size() {
+        for target in ["scanlines", "compressed"] {
+            let bent = bend(png(24, 16), target).unwrap();
+            let chunks = chunks(&bent).unwrap();
+            assert_eq!(
+                chunks.iter().filter(|chunk| &chunk.kind == b"IDAT").count(),
+                1
+            );
+            let img = image::load_from_memory_with_format(&bent, ImageFormat::Png).unwrap();
+            assert_eq!(img.dimensions(), (24, 16));
+        }
+    }
+
+    #[test]
+    fn only_raw_compressed_output_is_undecodable() {
+        let mut params = ParamValues::defaults(PARAMS);
+        assert!(PngBender.decodable(&params));
+        params.set("redeflate", ParamValue::Int(0));
+        assert!(PngBender.decodable(&params));
+        params.set("target", ParamValue::Int(1));
+        assert!(!PngBender.decodable(&params));
+    }
+
+    #[test]
+    fn interlaced_rows_follow_adam7() {
+        let header = Header {
+            width: 8,
+            height: 8,
+            bits_per_pixel: 32,
+            interlaced: true,
+        };
+        let rows = scanline_rows(&header).unwrap();
+        let widths: Vec<_> = rows
+            .runs
+            .iter()
+            .map(|&(row, count)| ((row - 1) / 4, count))
+            .collect();
+        assert_eq!(
+            widths,
+            [(1, 1), (1, 1), (2, 1), (2, 2), (4, 2), (4, 4), (8, 4)]
+        );
+        assert_eq!(rows.size, 271);
+    }
+
+    #[test]
+    fn repair_fixes_filter_bytes_and_length() {
+        let rows = Rows {
+            runs: vec![(3, 2), (2, 1)],
+            size: 8,
+        };
+        let mut scanlines = vec![9, 1, 1, 7, 1, 1, 255];
+        repair_scanlines(&mut scanlines, &rows);
+        assert_eq!(scanlines, [4, 1, 1, 2, 1, 1, 0, 0]);
+
+        let mut long = vec![5; 20];
+        repair_scanlines(&mut long, &rows);
+        assert_eq!(long, [0, 5, 5, 0, 5, 5, 0, 5]);
+    }
+
+    #[test]
+    fn oversized_headers_are_rejected_before_allocating() {
+        for (width, height) in [
+            (u32::MAX, u32::MAX),
+            (100_000, 100_000),
+            (1, u32::MAX),
+            (4000, 3000),
+        ] {
+            for interlaced in [false, true] {
+                for target in ["scanlines", "compressed"] {
+                    let error = bend(crafted(width, height, interlaced), target).unwrap_err();
+                    assert!(matches!(error, DatabendError::Decode(_)), "{}", error);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn raw_compressed_bends_take_any_header() {
+        let mut params = ParamValues::defaults(PARAMS);
+        params.set("target", ParamValue::Int(1));
+        params.set("redeflate", ParamValue::Int(0));
+        for (width, height) in [(u32::MAX, u32::MAX), (4000, 3000), (2, 2)] {
+            let bytes = crafted(width, height, false);
+            let bent = PngBender.bend(bytes, &params, &mut rng::from_seed(3), &Progress::none());
+            assert_eq!(
+                header(&chunks(&bent.unwrap()).unwrap()).unwrap().width,
+                width
+            );
+        }
+    }
+
+    #[test]
+    fn any_shortfall_in_the_input_stream_is_rejected() {
+        // 4x4 RGBA needs 4 rows of 17 bytes
+        let rows =
+            scanline_rows(&header(&chunks(&crafted(4, 4, false)).unwrap()).unwrap()).unwrap();
+        assert_eq!(rows.size, 68);
+        assert!(rows.check_inflated(68).is_ok());
+        for short in [0, 34, 67] {
+            assert!(rows.check_inflated(short).is_err(), "{}", short);
+        }
+        // The crafted IDAT inflates to 64 bytes, four short of the header
+        for target in ["scanlines", "compressed"] {
+            assert!(bend(crafted(4, 4, false), target).is_err());
+        }
+        assert!(bend(crafted(2, 2, false), "scanlines").is_ok());
+    }
+}
@@ -0,0 +1,107 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::error::DatabendError;
+
+/// Where a running effect reports how much of its work is done, and learns
+/// whether it should stop.
+///
+/// Effects call [`Progress::update`] as rows or layers finish; the callback
+/// receives the overall fraction in `0.0..=1.0`.
+#[derive(Clone, Copy)]
+pub struct Progress<'a> {
+    report: Option<&'a dyn Fn(f32)>,
+    cancel: Option<&'a AtomicBool>,
+    start: f32,
+    span: f32,
+}
+
+impl<'a> Progress<'a> {
+    /// Progress that nobody listens to and that can't be cancelled, for the
+    /// command line.
+    pub fn none() -> Self {
+        Self {
+            report: None,
+            cancel: None,
+            start: 0.0,
+            span: 1.0,
+        }
+    }
+
+    /// Progress sent to `report`. Setting `cancel` stops the run at its next
+    /// update.
+    pub fn new(report: &'a dyn Fn(f32), cancel: &'a AtomicBool) -> Self {
+        Self {
+            report: Some(report),
+            cancel: Some(cancel),
+            ..Self::none()
+        }
+    }
+
+    /// Reports that `done` out of `total` units of work are finished.
+    ///
+    /// Fails with [`DatabendError::Cancelled`] once cancellation was requested,
+    /// so effects can bail out with `?` between rows.
+    pub fn update(&self, done: usize, total: usize) -> Result<(), DatabendError> {
+        if let Some(report) = self.report
+            && total > 0
+        {
+            report(self.start + self.span * done.min(total) as f32 / total as f32);
+        }
+        self.check()
+    }
+
+    /// Fails with [`DatabendError::Cancelled`] once cancellation was requested.
+    pub fn check(&self) -> Result<(), DatabendError> {
+        match self.cancel {
+            Some(cancel) if cancel.load(Ordering::Relaxed) => Err(DatabendError::Cancelled),
+            _ => Ok(()),
+        }
+    }
+
+    /// The slice of this progress covered by part `index` of `count` equal
+    /// parts, so each step of a chain fills its own share of the gauge.
+    pub fn part(&self, index: usize, count: usize) -> Self {
+        let span = self.span / count.max(1) as f32;
+        Self {
+            start: self.start + span * index as f32,
+            span,
+            ..*self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    #[test]
+    fn parts_report_their_share_of_the_whole() {
+        let seen = RefCell::new(Vec::new());
+        let report = |fraction| seen.borrow_mut().push(fraction);
+        let cancel = AtomicBool::new(false);
+        let progress = Progress::new(&report, &cancel);
+
+        progress.update(1, 4).unwrap();
+        progress.part(1, 2).update(1, 2).unwrap();
+        progress.part(1, 2).part(1, 2).update(9, 4).unwrap();
+        progress.update(3, 0).unwrap();
+        assert_eq!(*seen.borrow(), [0.25, 0.75, 1.0]);
+    }
+
+    #[test]
+    fn cancelling_fails_the_next_update() {
+        let report = |_| {};
+        let cancel = AtomicBool::new(false);
+        let progress = Progress::new(&report, &cancel).part(0, 3);
+        assert!(progress.update(1, 2).is_ok());
+        cancel.store(true, Ordering::Relaxed);
+        assert!(matches!(
+            progress.update(2, 2),
+            Err(DatabendError::Cancelled)
+        ));
+        assert!(matches!(progress.check(), Err(DatabendError::Cancelled)));
+        assert!(Progress::none().update(1, 1).is_ok());
+    }
+}
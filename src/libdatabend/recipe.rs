@@ -0,0 +1,469 @@
+use std::fs;
+
+use image::Rgb;
+
+use super::effect::{ParamKind, ParamSpec, ParamValue, ParamValues};
+use super::error::DatabendError;
+use super::imageio;
+use super::progress::Progress;
+
+/// One effect of a recipe, with every parameter filled in.
+#[derive(Debug, Clone)]
+pub struct RecipeStep {
+    /// The name the effect goes by on the command line, e.g. `overexposure`.
+    pub effect: String,
+    pub specs: &'static [ParamSpec],
+    pub values: ParamValues,
+}
+
+/// A saved combination of effects, parameters and seed:
+///
+/// ```text
+/// seed = 42
+/// background = "000000"
+///
+/// [[effect]]
+/// name = "overexposure"
+/// exposure = 1.5
+/// ```
+///
+/// Several `[[effect]]` tables make a chain, which shares the one seed.
+///
+/// The format is line based, and every recipe is also valid TOML, but only
+/// this much of it is read:
+///
+/// - blank lines, and comments from a `#` outside a string to the line end
+/// - `[[effect]]` on a line of its own
+/// - `key = value`, where the key is bare and the value one of
+///   - an integer or float, with optional `_` separators
+///   - a `"basic"` string with the escapes `\" \\ \n \t \r \b \f \uXXXX \UXXXXXXXX`
+///   - a `'literal'` string, without escapes
+///
+/// Booleans, arrays, inline tables, dotted keys and multi-line strings are
+/// rejected with their line number.
+#[derive(Debug, Clone, Default)]
+pub struct Recipe {
+    pub seed: Option<u64>,
+    pub background: Option<Rgb<u8>>,
+    pub steps: Vec<RecipeStep>,
+}
+
+/// What the right-hand side of a `key = value` line holds.
+pub(super) enum Value {
+    /// Wide enough for any `u64` seed.
+    Int(i128),
+    Float(f64),
+    Str(String),
+}
+
+/// One `key = value` line.
+struct Entry {
+    line: usize,
+    key: String,
+    value: Value,
+}
+
+impl Recipe {
+    pub fn load(
+        path: &str,
+        lookup: impl Fn(&str) -> Option<&'static [ParamSpec]>,
+    ) -> Result<Self, DatabendError> {
+        Self::parse(&fs::read_to_string(path)?, lookup)
+    }
+
+    /// Parses a recipe, finding each effect's parameters through `lookup`.
+    /// Parameters that are left out keep their defaults; unknown keys, values
+    /// of the wrong type and values out of range are errors.
+    pub fn parse(
+        text: &str,
+        lookup: impl Fn(&str) -> Option<&'static [ParamSpec]>,
+    ) -> Result<Self, DatabendError> {
+        let mut top = Vec::new();
+        // Each `[[effect]]` with the line it starts on
+        let mut tables: Vec<(usize, Vec<Entry>)> = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let number = index + 1;
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line == "[[effect]]" {
+                tables.push((number, Vec::new()));
+                continue;
+            }
+            if line.starts_with('[') {
+                return Err(invalid(number, format!("unknown table {}", line)));
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid(number, "expected `key = value`".to_string()))?;
+            let key = key.trim().to_string();
+            let value = parse_value(value.trim())
+                .ok_or_else(|| invalid(number, value_error(&key, value)))?;
+            let entries = match tables.last_mut() {
+                Some((_, entries)) => entries,
+                None => &mut top,
+            };
+            if entries.iter().any(|entry| entry.key == key) {
+                return Err(invalid(number, format!("{} is set twice", key)));
+            }
+            entries.push(Entry {
+                line: number,
+                key,
+                value,
+            });
+        }
+
+        let mut recipe = Recipe::default();
+        for entry in top {
+            match (entry.key.as_str(), entry.value) {
+                ("seed", Value::Int(seed)) if u64::try_from(seed).is_ok() => {
+                    recipe.seed = Some(seed as u64);
+                }
+                ("seed", _) => {
+                    return Err(invalid(
+                        entry.line,
+                        "seed must be a whole number".to_string(),
+                    ));
+                }
+                ("background", Value::Str(color)) => {
+                    recipe.background = Some(imageio::parse_color(&color).ok_or_else(|| {
+                        invalid(
+                            entry.line,
+                            "background must be a \"RRGGBB\" colour".to_string(),
+                        )
+                    })?);
+                }
+                ("background", _) => {
+                    return Err(invalid(
+                        entry.line,
+                        "background must be a \"RRGGBB\" colour".to_string(),
+                    ));
+                }
+                (key, _) => {
+                    return Err(invalid(
+                        entry.line,
+                        format!("unknown key {} (effect settings go under [[effect]])", key),
+                    ));
+                }
+            }
+        }
+        for (line, entries) in tables {
+            recipe.steps.push(step(line, entries, &lookup)?);
+        }
+        if recipe.steps.is_empty() {
+            return Err(DatabendError::InvalidRecipe(
+                "it has no [[effect]]".to_string(),
+            ));
+        }
+        Ok(recipe)
+    }
+
+    /// The recipe as text that `parse` reads back, with every parameter
+    /// written out.
+    pub fn to_text(&self) -> String {
+        let mut out = String::from("# Shutterbomb recipe\n");
+        if let Some(seed) = self.seed {
+            out.push_str(&format!("seed = {}\n", seed));
+        }
+        if let Some(background) = self.background {
+            out.push_str(&format!(
+                "background = {}\n",
+                quote(&imageio::format_color(background))
+            ));
+        }
+        for step in &self.steps {
+            out.push_str(&format!("\n[[effect]]\nname = {}\n", quote(&step.effect)));
+            for spec in step.specs {
+                let value = step.values.get(spec.name).unwrap_or(spec.default_value());
+                let text = match spec.kind {
+                    ParamKind::Choice(_) => quote(&spec.format_value(value)),
+                    ParamKind::Int { .. } | ParamKind::Float { .. } => spec.format_value(value),
+                };
+                out.push_str(&format!("{} = {}\n", spec.name, text));
+            }
+        }
+        out
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), DatabendError> {
+        imageio::write_atomically(path, &Progress::none(), |partial| {
+            Ok(fs::write(partial, self.to_text())?)
+        })
+    }
+}
+
+/// Builds the step for the `[[effect]]` table that starts on `line`.
+fn step(
+    line: usize,
+    entries: Vec<Entry>,
+    lookup: impl Fn(&str) -> Option<&'static [ParamSpec]>,
+) -> Result<RecipeStep, DatabendError> {
+    let (name, name_line) = match entries.iter().find(|entry| entry.key == "name") {
+        Some(Entry {
+            value: Value::Str(name),
+            line,
+            ..
+        }) => (name.clone(), *line),
+        Some(entry) => {
+            return Err(invalid(
+                entry.line,
+                "name must be a \"quoted\" string".to_string(),
+            ));
+        }
+        None => return Err(invalid(line, "[[effect]] needs a name".to_string())),
+    };
+    let specs =
+        lookup(&name).ok_or_else(|| invalid(name_line, format!("unknown effect '{}'", name)))?;
+
+    let mut values = ParamValues::defaults(specs);
+    for entry in entries.iter().filter(|entry| entry.key != "name") {
+        let spec = specs
+            .iter()
+            .find(|spec| spec.name == entry.key)
+            .ok_or_else(|| {
+                invalid(
+                    entry.line,
+                    format!("{} has no parameter {}", name, entry.key),
+                )
+            })?;
+        let value =
+            param_value(spec, &entry.value).map_err(|reason| invalid(entry.line, reason))?;
+        values.set(spec.name, value);
+    }
+    Ok(RecipeStep {
+        effect: name,
+        specs,
+        values,
+    })
+}
+
+/// Reads `value` as the parameter `spec`, checking its type and range.
+pub(super) fn param_value(spec: &ParamSpec, value: &Value) -> Result<ParamValue, String> {
+    match (spec.kind, value) {
+        // Out of i64 means out of range, which `check` reports
+        (ParamKind::Int { .. }, Value::Int(v)) => {
+            Ok(ParamValue::Int(i64::try_from(*v).unwrap_or(i64::MAX)))
+        }
+        (ParamKind::Float { .. }, Value::Int(v)) => Ok(ParamValue::Float(*v as f64)),
+        (ParamKind::Float { .. }, Value::Float(v)) => Ok(ParamValue::Float(*v)),
+        (ParamKind::Choice(_), Value::Str(option)) => spec.parse(option),
+        (ParamKind::Int { .. }, _) => return Err(format!("{} must be a whole number", spec.name)),
+        (ParamKind::Float { .. }, _) => return Err(format!("{} must be a number", spec.name)),
+        (ParamKind::Choice(options), _) => {
+            return Err(format!(
+                "{} must be one of \"{}\"",
+                spec.name,
+                options.join("\", \"")
+            ));
+        }
+    }
+    .and_then(|value| spec.check(value))
+    .map_err(|error| match error {
+        DatabendError::InvalidParameter { name, reason } => format!("{}: {}", name, reason),
+        error => error.to_string(),
+    })
+}
+
+fn invalid(line: usize, reason: String) -> DatabendError {
+    DatabendError::InvalidRecipe(format!("line {}: {}", line, reason))
+}
+
+/// Cuts a line at the first `#` that isn't inside a string.
+pub(super) fn strip_comment(line: &str) -> &str {
+    // The quote that opened the string we are in, if any
+    let (mut quote, mut escaped) = (None, false);
+    for (index, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quote == Some('"') => escaped = true,
+            '"' | '\'' if quote.is_none() => quote = Some(c),
+            c if quote == Some(c) => quote = None,
+            '#' if quote.is_none() => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Why `value` couldn't be read for `key`.
+pub(super) fn value_error(key: &str, value: &str) -> String {
+    match value.trim() {
+        "true" | "false" => format!("{} can't be a boolean", key),
+        value if value.starts_with(['[', '{']) => {
+            format!("{} can't be an array or inline table", key)
+        }
+        value if value.starts_with("\"\"\"") || value.starts_with("'''") => {
+            format!("{} can't be a multi-line string", key)
+        }
+        _ => format!("{} needs a number or a \"quoted\" string", key),
+    }
+}
+
+/// Reads a basic `"string"`, a literal `'string'`, an integer or a float.
+pub(super) fn parse_value(input: &str) -> Option<Value> {
+    if let Some(rest) = input.strip_prefix('\'') {
+        let text = rest.strip_suffix('\'')?;
+        return (!text.contains(['\'', '\n'])).then(|| Value::Str(text.to_string()));
+    }
+    if let Some(rest) = input.strip_prefix('"') {
+        let mut text = String::new();
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return chars.as_str().is_empty().then_some(Value::Str(text)),
+                '\\' => text.push(match chars.next()? {
+                    'n' => '\n',
+                    't' => '\t',
+                    'r' => '\r',
+                    'b' => '\u{8}',
+                    'f' => '\u{c}',
+                    'u' => unicode_escape(&mut chars, 4)?,
+                    'U' => unicode_escape(&mut chars, 8)?,
+                    c @ ('"' | '\\') => c,
+                    _ => return None,
+                }),
+                c if c.is_control() && c != '\t' => return None,
+                c => text.push(c),
+            }
+        }
+        return None;
+    }
+    let number = input.replace('_', "");
+    if let Ok(int) = number.parse() {
+        return Some(Value::Int(int));
+    }
+    number.parse().ok().map(Value::Float)
+}
+
+/// Reads the `digits` hex digits after `\u` or `\U`.
+fn unicode_escape(chars: &mut std::str::Chars, digits: usize) -> Option<char> {
+    let hex: String = chars.by_ref().take(digits).collect();
+    if hex.len() != digits {
+        return None;
+    }
+    char::from_u32(u32::from_str_radix(&hex, 16).ok()?)
+}
+
+/// Writes `text` as a basic string that `parse_value` reads back unchanged.
+pub(super) fn quote(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04X}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<&'static [ParamSpec]> {
+        super::super::find_effect(name).map(|effect| effect.params())
+    }
+
+    fn parse(text: &str) -> Result<Recipe, DatabendError> {
+        Recipe::parse(text, lookup)
+    }
+
+    fn string(input: &str) -> Option<String> {
+        match parse_value(input)? {
+            Value::Str(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn written_recipes_read_back_unchanged() {
+        let text = "seed = 18446744073709551615\nbackground = \"1a2b3c\"\n\n\
+                    [[effect]]\nname = \"overexposure\"\nexposure = 2.5\n\n\
+                    [[effect]]\nname = \"echo\"\nstream = \"channels\"\ndelay = 1200\n";
+        let recipe = parse(text).unwrap();
+        assert_eq!(recipe.seed, Some(u64::MAX));
+        assert_eq!(recipe.background, Some(Rgb([0x1a, 0x2b, 0x3c])));
+        assert_eq!(recipe.steps.len(), 2);
+        assert_eq!(recipe.steps[0].values.float("exposure"), 2.5);
+        assert_eq!(recipe.steps[1].values.int("stream"), 1);
+
+        let written = recipe.to_text();
+        let again = parse(&written).unwrap();
+        assert_eq!(again.to_text(), written);
+        assert_eq!(again.steps[1].values.int("delay"), 1200);
+    }
+
+    #[test]
+    fn reads_literal_strings_comments_and_separators() {
+        let recipe = parse(
+            "seed = 1_000 # a comment\n\
+             [[effect]] \n\
+             name = 'overexposure' # 'quoted' # twice\n\
+             boost = 100\n",
+        )
+        .unwrap();
+        assert_eq!(recipe.seed, Some(1000));
+        assert_eq!(recipe.steps[0].effect, "overexposure");
+        assert_eq!(recipe.steps[0].values.float("boost"), 100.0);
+        assert_eq!(strip_comment("name = \"a # b\" # c"), "name = \"a # b\" ");
+        assert_eq!(strip_comment("name = 'a \\' # c"), "name = 'a \\' ");
+    }
+
+    #[test]
+    fn quoted_strings_round_trip() {
+        for text in [
+            "plain",
+            "say \"hi\" \\ there",
+            "line\nbreak\ttab\r",
+            "\u{1}\u{7f}é💣",
+            "'",
+        ] {
+            assert_eq!(
+                string(&quote(text)).as_deref(),
+                Some(text),
+                "{:?}",
+                quote(text)
+            );
+        }
+        assert_eq!(string("\"\\u00e9\\U0001F4A3\"").as_deref(), Some("é💣"));
+        assert_eq!(string("'C:\\path'").as_deref(), Some("C:\\path"));
+        assert!(string("\"unterminated").is_none());
+        assert!(string("\"bad \\q escape\"").is_none());
+        assert!(string("\"trailing\" junk").is_none());
+        assert!(string("'it's'").is_none());
+    }
+
+    #[test]
+    fn rejects_what_recipes_do_not_support() {
+        let error = |text: &str| parse(text).unwrap_err().to_string();
+        let effect = "[[effect]]\nname = \"overexposure\"\n";
+        let boolean = error(&format!("{}exposure = true", effect));
+        assert!(boolean.contains("line 3: exposure can't be a boolean"));
+        assert!(error(&format!("{}exposure = [1, 2]", effect)).contains("array or inline table"));
+        assert!(
+            error(&format!("{}exposure = {{ a = 1 }}", effect)).contains("array or inline table")
+        );
+        assert!(
+            error(&format!("{}exposure = \"\"\"x\"\"\"", effect)).contains("multi-line string")
+        );
+        assert!(error(&format!("{}exposure = 9", effect)).contains("line 3"));
+        assert!(
+            error(&format!("{}iso = 100", effect)).contains("overexposure has no parameter iso")
+        );
+        assert!(error(&format!("{}exposure = 1\nexposure = 2", effect)).contains("set twice"));
+        assert!(error("[[effect]]\nexposure = 1").contains("line 1: [[effect]] needs a name"));
+        assert!(error("[[effect]]\nname = \"nope\"").contains("unknown effect 'nope'"));
+        assert!(error("[effects]").contains("unknown table"));
+        assert!(
+            error("seed = -1\n[[effect]]\nname = \"echo\"").contains("seed must be a whole number")
+        );
+        assert!(error("seed = 1").contains("no [[effect]]"));
+    }
+}
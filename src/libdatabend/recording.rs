@@ -0,0 +1,376 @@
+use std::{
+    fs,
+    io::Cursor,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use image::{
+    Delay, DynamicImage, Frame, ImageFormat, RgbaImage,
+    codecs::gif::{GifEncoder, Repeat},
+    imageops::{self, FilterType},
+};
+
+use super::effect::{ParamKind, ParamSpec, ParamValues};
+use super::error::DatabendError;
+use super::imageio;
+use super::interactive::Canvas;
+use super::pngbend;
+use super::progress::Progress;
+
+const RECORD: ParamSpec = ParamSpec {
+    name: "record",
+    label: "Record Session as",
+    kind: ParamKind::Choice(&["off", "gif", "apng"]),
+    default: 0.0,
+};
+
+const CAPTURE: ParamSpec = ParamSpec {
+    name: "capture",
+    label: "Capture a Frame on",
+    kind: ParamKind::Choice(&["keypress", "interval"]),
+    default: 0.0,
+};
+
+const INTERVAL: ParamSpec = ParamSpec {
+    name: "interval",
+    label: "Capture Interval in ms",
+    kind: ParamKind::Int {
+        min: 50,
+        max: 60_000,
+        step: 50,
+    },
+    default: 500.0,
+};
+
+const FRAME_DELAY: ParamSpec = ParamSpec {
+    name: "frame_delay",
+    label: "Frame Delay in ms",
+    kind: ParamKind::Int {
+        min: 10,
+        max: 10_000,
+        step: 10,
+    },
+    default: 100.0,
+};
+
+const MAX_FRAMES: ParamSpec = ParamSpec {
+    name: "max_frames",
+    label: "Max Frames",
+    kind: ParamKind::Int {
+        min: 2,
+        max: 10_000,
+        step: 50,
+    },
+    default: 300.0,
+};
+
+const DOWNSCALE: ParamSpec = ParamSpec {
+    name: "downscale",
+    label: "Downscale Frames by",
+    kind: ParamKind::Int {
+        min: 1,
+        max: 16,
+        step: 1,
+    },
+    default: 2.0,
+};
+
+/// Writes every key of the session to `<stem>_keys.log` for `shutterbomb replay`.
+const KEY_LOG: ParamSpec = ParamSpec {
+    name: "key_log",
+    label: "Save Key Log",
+    kind: ParamKind::Choice(&["no", "yes"]),
+    default: 1.0,
+};
+
+/// The fields of the TUI's recording popup. They describe how a session is
+/// captured, not how it bends the image, so no effect lists them among its
+/// params and they never reach recipes or key logs.
+pub const SETTINGS: &[ParamSpec] = &[
+    RECORD,
+    CAPTURE,
+    INTERVAL,
+    FRAME_DELAY,
+    MAX_FRAMES,
+    DOWNSCALE,
+    KEY_LOG,
+];
+
+/// How an interactive session is recorded and logged.
+#[derive(Debug, Clone, Copy)]
+pub struct SessionSettings {
+    /// The animation to record, or `None` to record nothing.
+    pub record: Option<RecordOptions>,
+    /// Write every key to `<stem>_keys.log` for `shutterbomb replay`.
+    pub key_log: bool,
+}
+
+impl SessionSettings {
+    /// The settings held by `values`, which are checked against [`SETTINGS`].
+    pub fn from_values(values: &ParamValues) -> Result<Self, DatabendError> {
+        values.check(SETTINGS)?;
+        let format = match values.int(RECORD.name) {
+            1 => Some(AnimationFormat::Gif),
+            2 => Some(AnimationFormat::Apng),
+            _ => None,
+        };
+        let capture = match values.int(CAPTURE.name) {
+            1 => Capture::Interval(Duration::from_millis(values.int(INTERVAL.name) as u64)),
+            _ => Capture::Keypress,
+        };
+        Ok(Self {
+            record: format.map(|format| RecordOptions {
+                format,
+                capture,
+                frame_delay: Duration::from_millis(values.int(FRAME_DELAY.name) as u64),
+                max_frames: values.int(MAX_FRAMES.name) as usize,
+                downscale: values.int(DOWNSCALE.name) as u32,
+            }),
+            key_log: values.int(KEY_LOG.name) == 1,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnimationFormat {
+    Gif,
+    Apng,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Capture {
+    Keypress,
+    Interval(Duration),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RecordOptions {
+    pub format: AnimationFormat,
+    pub capture: Capture,
+    pub frame_delay: Duration,
+    pub max_frames: usize,
+    pub downscale: u32,
+}
+
+/// Frames captured from an interactive session, written out as one animation.
+pub struct Recorder {
+    options: RecordOptions,
+    frames: Vec<RgbaImage>,
+    last_capture: Instant,
+}
+
+impl Recorder {
+    /// Starts a recording whose first frame is the untouched canvas.
+    pub fn new(options: RecordOptions, canvas: &Canvas) -> Self {
+        let mut recorder = Self {
+            options,
+            frames: Vec::new(),
+            last_capture: Instant::now(),
+        };
+        recorder.capture(canvas);
+        recorder
+    }
+
+    pub fn options(&self) -> &RecordOptions {
+        &self.options
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Called after every key that changed the canvas.
+    pub fn on_key(&mut self, canvas: &Canvas) {
+        if self.options.capture == Capture::Keypress {
+            self.capture(canvas);
+        }
+    }
+
+    /// Called on every turn of the event loop.
+    pub fn on_tick(&mut self, canvas: &Canvas) {
+        if let Capture::Interval(interval) = self.options.capture
+            && self.last_capture.elapsed() >= interval
+        {
+            self.capture(canvas);
+        }
+    }
+
+    /// Once `max_frames` is reached, new captures replace the last frame, so
+    /// the animation always ends on the latest state.
+    fn capture(&mut self, canvas: &Canvas) {
+        let Ok(frame) = canvas.image().map(|image| image.to_rgba8()) else {
+            return;
+        };
+        let scale = self.options.downscale.max(1);
+        let frame = if scale > 1 {
+            let (width, height) = (
+                (canvas.width / scale).max(1),
+                (canvas.height / scale).max(1),
+            );
+            imageops::resize(&frame, width, height, FilterType::Triangle)
+        } else {
+            frame
+        };
+        if self.frames.len() >= self.options.max_frames {
+            self.frames.pop();
+        }
+        self.frames.push(frame);
+        self.last_capture = Instant::now();
+    }
+
+    /// Where the animation goes: next to the still output, as
+    /// `<stem>_session.gif` or `<stem>_session.png`.
+    pub fn output_path(&self, still_path: &str) -> String {
+        let still = Path::new(still_path);
+        let stem = still
+            .file_stem()
+            .map_or("output".into(), |stem| stem.to_string_lossy());
+        let extension = match self.options.format {
+            AnimationFormat::Gif => "gif",
+            AnimationFormat::Apng => "png",
+        };
+        still
+            .with_file_name(format!("{}_session.{}", stem, extension))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Encodes every frame into `output_path`, reporting one step per frame.
+    pub fn save(&self, output_path: &str, progress: &Progress) -> Result<(), DatabendError> {
+        let bytes = match self.options.format {
+            AnimationFormat::Gif => self.encode_gif(progress)?,
+            AnimationFormat::Apng => self.encode_apng(progress)?,
+        };
+        imageio::write_atomically(output_path, progress, |partial| {
+            Ok(fs::write(partial, &bytes)?)
+        })
+    }
+
+    fn encode_gif(&self, progress: &Progress) -> Result<Vec<u8>, DatabendError> {
+        let delay = Delay::from_saturating_duration(self.options.frame_delay);
+        let mut bytes = Vec::new();
+        {
+            // Speed 10 of 30: quantizing hundreds of frames at the default
+            // speed of 1 takes minutes
+            let mut encoder = GifEncoder::new_with_speed(&mut bytes, 10);
+            encoder
+                .set_repeat(Repeat::Infinite)
+                .map_err(DatabendError::Encode)?;
+            for (index, frame) in self.frames.iter().enumerate() {
+                progress.update(index, self.frames.len())?;
+                encoder
+                    .encode_frame(Frame::from_parts(frame.clone(), 0, 0, delay))
+                    .map_err(DatabendError::Encode)?;
+            }
+        }
+        Ok(bytes)
+    }
+
+    /// Builds an APNG from one PNG per frame: the first frame's IDAT is the
+    /// default image, the others become `fdAT` chunks, each behind an `fcTL`.
+    fn encode_apng(&self, progress: &Progress) -> Result<Vec<u8>, DatabendError> {
+        let first = self.frames.first().ok_or_else(|| {
+            DatabendError::InvalidDimensions("the recording has no frames".to_string())
+        })?;
+        let delay_ms = self.options.frame_delay.as_millis().min(u16::MAX as u128) as u16;
+
+        let mut out = pngbend::SIGNATURE.to_vec();
+        let mut sequence = 0u32;
+        for (index, frame) in self.frames.iter().enumerate() {
+            progress.update(index, self.frames.len())?;
+            let mut png = Vec::new();
+            DynamicImage::ImageRgba8(frame.clone())
+                .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+                .map_err(DatabendError::Encode)?;
+            let chunks = pngbend::chunks(&png)?;
+
+            if index == 0 {
+                copy_chunk(&mut out, &chunks, b"IHDR");
+                let mut actl = Vec::new();
+                actl.extend_from_slice(&(self.frames.len() as u32).to_be_bytes());
+                actl.extend_from_slice(&0u32.to_be_bytes()); // loop forever
+                pngbend::write_chunk(&mut out, b"acTL", &actl);
+            }
+
+            let mut fctl = Vec::new();
+            fctl.extend_from_slice(&sequence.to_be_bytes());
+            fctl.extend_from_slice(&first.width().to_be_bytes());
+            fctl.extend_from_slice(&first.height().to_be_bytes());
+            fctl.extend_from_slice(&[0; 8]); // x and y offset
+            fctl.extend_from_slice(&delay_ms.to_be_bytes());
+            fctl.extend_from_slice(&1000u16.to_be_bytes());
+            fctl.extend_from_slice(&[0, 0]); // dispose: none, blend: source
+            pngbend::write_chunk(&mut out, b"fcTL", &fctl);
+            sequence += 1;
+
+            for chunk in chunks.iter().filter(|chunk| &chunk.kind == b"IDAT") {
+                if index == 0 {
+                    pngbend::write_chunk(&mut out, b"IDAT", chunk.data);
+                } else {
+                    let mut fdat = sequence.to_be_bytes().to_vec();
+                    fdat.extend_from_slice(chunk.data);
+                    pngbend::write_chunk(&mut out, b"fdAT", &fdat);
+                    sequence += 1;
+                }
+            }
+        }
+        pngbend::write_chunk(&mut out, b"IEND", &[]);
+        Ok(out)
+    }
+}
+
+/// Copies the first `kind` chunk of an encoded frame.
+fn copy_chunk(out: &mut Vec<u8>, chunks: &[pngbend::Chunk], kind: &[u8; 4]) {
+    if let Some(chunk) = chunks.iter().find(|chunk| &chunk.kind == kind) {
+        pngbend::write_chunk(out, kind, chunk.data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libdatabend::{
+        effect::ParamValue, jackstauberism, newnormal, session_params, synestesia,
+    };
+
+    #[test]
+    fn default_settings_log_keys_without_recording() {
+        let settings = SessionSettings::from_values(&ParamValues::defaults(SETTINGS)).unwrap();
+        assert!(settings.record.is_none());
+        assert!(settings.key_log);
+    }
+
+    #[test]
+    fn settings_build_record_options() {
+        let mut values = ParamValues::defaults(SETTINGS);
+        values.set(RECORD.name, ParamValue::Int(2));
+        values.set(CAPTURE.name, ParamValue::Int(1));
+        values.set(INTERVAL.name, ParamValue::Int(250));
+        values.set(KEY_LOG.name, ParamValue::Int(0));
+        let settings = SessionSettings::from_values(&values).unwrap();
+        let record = settings.record.unwrap();
+        assert_eq!(record.format, AnimationFormat::Apng);
+        assert_eq!(
+            record.capture,
+            Capture::Interval(Duration::from_millis(250))
+        );
+        assert_eq!((record.max_frames, record.downscale), (300, 2));
+        assert!(!settings.key_log);
+
+        values.set(DOWNSCALE.name, ParamValue::Int(0));
+        assert!(SessionSettings::from_values(&values).is_err());
+    }
+
+    #[test]
+    fn no_effect_lists_the_settings_as_params() {
+        for name in [synestesia::ID, jackstauberism::ID, newnormal::ID] {
+            let specs = session_params(name).unwrap();
+            assert!(
+                specs
+                    .iter()
+                    .all(|spec| SETTINGS.iter().all(|setting| setting.name != spec.name))
+            );
+        }
+    }
+}
@@ -0,0 +1,314 @@
+use std::sync::Arc;
+
+use image::{
+    DynamicImage, GenericImageView, GrayImage, ImageBuffer, Luma,
+    imageops::{self, FilterType},
+};
+
+use super::error::DatabendError;
+use super::imageio;
+
+/// How strongly an effect shows at each pixel, from 0 (the input is kept)
+/// to 1 (the bent pixel replaces it).
+pub type Weights = ImageBuffer<Luma<f32>, Vec<f32>>;
+
+/// A coordinate or size in pixels, or in percent of the image side.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Length {
+    Pixels(f64),
+    Percent(f64),
+}
+
+impl Length {
+    fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        let (number, percent) = match input.strip_suffix('%') {
+            Some(number) => (number.trim(), true),
+            None => (input, false),
+        };
+        let value = number
+            .parse::<f64>()
+            .ok()
+            .filter(|value| value.is_finite() && *value >= 0.0)?;
+        Some(if percent {
+            Length::Percent(value)
+        } else {
+            Length::Pixels(value)
+        })
+    }
+
+    /// In pixels along a side of `side` pixels, rounded and kept on the image.
+    fn resolve(self, side: u32) -> u32 {
+        let pixels = match self {
+            Length::Pixels(pixels) => pixels,
+            Length::Percent(percent) => percent / 100.0 * side as f64,
+        };
+        pixels.round().min(side as f64) as u32
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Rect {
+    x: Length,
+    y: Length,
+    width: Length,
+    height: Length,
+}
+
+#[derive(Debug, Clone)]
+enum Area {
+    Rects(Vec<Rect>),
+    /// A grayscale image whose luminance is the strength, stretched to fit.
+    Mask(Arc<GrayImage>),
+}
+
+/// The part of an image an effect may change; everywhere else the input's
+/// pixels are kept.
+#[derive(Debug, Clone)]
+pub struct Region {
+    /// As typed: `x,y,w,h` rectangles separated by `;`, or a mask image path.
+    pub spec: String,
+    /// Roughly how many pixels the edge fades over; 0 keeps it hard.
+    pub feather: u32,
+    area: Area,
+}
+
+impl Region {
+    /// Reads rectangles such as `0,0,100%,40%` (several separated by `;`),
+    /// each value in pixels or percent, or else loads `spec` as a mask image.
+    pub fn parse(spec: &str, feather: u32) -> Result<Self, DatabendError> {
+        let spec = spec.trim();
+        let area = match parse_rects(spec) {
+            Some(rects) => Area::Rects(rects),
+            // Anything with a comma was meant as rectangles
+            None if spec.contains(',') => {
+                return Err(DatabendError::invalid_parameter(
+                    "region",
+                    format!("'{}' is not a list of x,y,width,height rectangles", spec),
+                ));
+            }
+            None => {
+                let mask = imageio::load(spec).map_err(|error| {
+                    DatabendError::invalid_parameter(
+                        "region",
+                        format!("mask '{}': {}", spec, error),
+                    )
+                })?;
+                Area::Mask(Arc::new(mask.to_luma8()))
+            }
+        };
+        Ok(Self {
+            spec: spec.to_string(),
+            feather,
+            area,
+        })
+    }
+
+    /// The strength of the effect at every pixel of a `width` x `height`
+    /// image, with the edges feathered.
+    pub fn weights(&self, width: u32, height: u32) -> Weights {
+        let weights = match &self.area {
+            Area::Rects(rects) => {
+                let mut weights = Weights::new(width, height);
+                for rect in rects {
+                    let (left, top) = (rect.x.resolve(width), rect.y.resolve(height));
+                    let right = (left + rect.width.resolve(width)).min(width);
+                    let bottom = (top + rect.height.resolve(height)).min(height);
+                    for y in top..bottom {
+                        for x in left..right {
+                            weights.put_pixel(x, y, Luma([1.0]));
+                        }
+                    }
+                }
+                weights
+            }
+            Area::Mask(mask) => {
+                let mask = imageops::resize(&**mask, width, height, FilterType::Triangle);
+                Weights::from_fn(width, height, |x, y| {
+                    Luma([mask.get_pixel(x, y)[0] as f32 / 255.0])
+                })
+            }
+        };
+        if self.feather > 0 {
+            // A blur with sigma f/2 fades the edge over about f pixels
+            imageops::fast_blur(&weights, self.feather as f32 / 2.0)
+        } else {
+            weights
+        }
+    }
+
+    /// Keeps `original` outside the region and `bent` inside it.
+    pub fn apply(
+        &self,
+        original: &DynamicImage,
+        bent: &DynamicImage,
+    ) -> Result<DynamicImage, DatabendError> {
+        blend(
+            original,
+            bent,
+            &self.weights(original.width(), original.height()),
+        )
+    }
+}
+
+/// Mixes `bent` into `original` pixel by pixel, at 16 bits per channel so
+/// deep inputs keep their depth where they are left alone.
+pub fn blend(
+    original: &DynamicImage,
+    bent: &DynamicImage,
+    weights: &Weights,
+) -> Result<DynamicImage, DatabendError> {
+    if bent.dimensions() != original.dimensions() || weights.dimensions() != original.dimensions() {
+        return Err(DatabendError::InvalidDimensions(format!(
+            "the bent image is {}x{}, but the input is {}x{}",
+            bent.width(),
+            bent.height(),
+            original.width(),
+            original.height()
+        )));
+    }
+    let mut out = original.to_rgba16();
+    let has_alpha = original.color().has_alpha() || bent.color().has_alpha();
+    let bent = bent.to_rgba16();
+    for ((pixel, bent), weight) in out.pixels_mut().zip(bent.pixels()).zip(weights.pixels()) {
+        let weight = weight[0];
+        for (channel, bent) in pixel.0.iter_mut().zip(bent.0) {
+            *channel = (*channel as f32 + (bent as f32 - *channel as f32) * weight).round() as u16;
+        }
+    }
+    let out = DynamicImage::ImageRgba16(out);
+    Ok(if has_alpha {
+        out
+    } else {
+        DynamicImage::ImageRgb16(out.to_rgb16())
+    })
+}
+
+fn parse_rects(spec: &str) -> Option<Vec<Rect>> {
+    spec.split(';')
+        .map(|rect| {
+            let values: Vec<Length> = rect.split(',').map(Length::parse).collect::<Option<_>>()?;
+            match values[..] {
+                [x, y, width, height] => Some(Rect {
+                    x,
+                    y,
+                    width,
+                    height,
+                }),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libdatabend::testing::TempDir;
+
+    /// The pixels at full strength, as `(x, y)` in row order.
+    fn covered(region: &Region, width: u32, height: u32) -> Vec<(u32, u32)> {
+        let weights = region.weights(width, height);
+        weights
+            .enumerate_pixels()
+            .filter(|(_, _, weight)| weight[0] == 1.0)
+            .map(|(x, y, _)| (x, y))
+            .collect()
+    }
+
+    #[test]
+    fn rectangles_mix_pixels_and_percent() {
+        let region = Region::parse(" 1,0,50%,2 ; 0,75%,1,100% ", 0).unwrap();
+        assert_eq!(region.spec, "1,0,50%,2 ; 0,75%,1,100%");
+        assert_eq!(
+            covered(&region, 4, 4),
+            [(1, 0), (2, 0), (1, 1), (2, 1), (0, 3)]
+        );
+        assert!(
+            region
+                .weights(4, 4)
+                .pixels()
+                .all(|weight| matches!(weight[0], 0.0 | 1.0))
+        );
+    }
+
+    #[test]
+    fn rectangles_are_clipped_to_the_image() {
+        let region = Region::parse("2,2,1000,1000;10,10,5,5;0,0,0,4", 0).unwrap();
+        assert_eq!(covered(&region, 3, 3), [(2, 2)]);
+        let whole = Region::parse("0,0,100%,100%", 0).unwrap();
+        assert_eq!(covered(&whole, 1, 1), [(0, 0)]);
+    }
+
+    #[test]
+    fn malformed_rectangles_are_invalid_parameters() {
+        for spec in [
+            "0,0,10",
+            "0,0,10,10,10",
+            "0,0,-1,10",
+            "a,0,1,1",
+            "0,0,1,1;",
+            "0,0,inf,1",
+        ] {
+            match Region::parse(spec, 0) {
+                Err(DatabendError::InvalidParameter { name, .. }) => assert_eq!(name, "region"),
+                other => panic!("{:?} parsed to {:?}", spec, other.map(|region| region.spec)),
+            }
+        }
+    }
+
+    #[test]
+    fn a_spec_without_commas_is_a_mask_path() {
+        let dir = TempDir::new("region");
+        let path = dir.join("mask.png");
+        GrayImage::from_fn(2, 1, |x, _| Luma([if x == 0 { 0 } else { 255 }]))
+            .save(&path)
+            .unwrap();
+        let region = Region::parse(&path, 0);
+        std::fs::remove_file(&path).unwrap();
+
+        let weights = region.unwrap().weights(2, 1);
+        assert_eq!(weights.get_pixel(0, 0)[0], 0.0);
+        assert_eq!(weights.get_pixel(1, 0)[0], 1.0);
+        assert!(matches!(
+            Region::parse(&path, 0),
+            Err(DatabendError::InvalidParameter { name, .. }) if name == "region"
+        ));
+    }
+
+    #[test]
+    fn feathering_fades_the_edge_and_keeps_the_middle() {
+        let region = Region::parse("8,8,16,16", 4).unwrap();
+        let weights = region.weights(32, 32);
+        assert!(
+            weights
+                .pixels()
+                .all(|weight| (0.0..=1.0).contains(&weight[0]))
+        );
+        assert!(weights.get_pixel(16, 16)[0] > 0.99);
+        assert!(weights.get_pixel(0, 0)[0] < 0.01);
+        let edge = weights.get_pixel(8, 16)[0];
+        assert!(edge > 0.2 && edge < 0.8, "{}", edge);
+    }
+
+    #[test]
+    fn apply_keeps_the_original_outside_the_region() {
+        let solid =
+            |rgb| DynamicImage::ImageRgb8(image::RgbImage::from_pixel(2, 1, image::Rgb(rgb)));
+        let (original, bent) = (solid([10, 20, 30]), solid([200, 0, 90]));
+        let out = Region::parse("1,0,1,1", 0)
+            .unwrap()
+            .apply(&original, &bent)
+            .unwrap()
+            .to_rgb8();
+        assert_eq!(out.get_pixel(0, 0).0, [10, 20, 30]);
+        assert_eq!(out.get_pixel(1, 0).0, [200, 0, 90]);
+
+        let small = DynamicImage::new_rgb8(1, 1);
+        let region = Region::parse("0,0,1,1", 0).unwrap();
+        assert!(matches!(
+            region.apply(&original, &small),
+            Err(DatabendError::InvalidDimensions(_))
+        ));
+    }
+}
@@ -0,0 +1,15 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// The generator every effect draws from. ChaCha8 produces the same stream on
+/// every platform and across `rand` releases, so a seed always reproduces a result.
+pub type DatabendRng = ChaCha8Rng;
+
+/// Returns `seed`, or a fresh random one when none was given.
+pub fn resolve_seed(seed: Option<u64>) -> u64 {
+    seed.unwrap_or_else(|| rand::rng().random())
+}
+
+pub fn from_seed(seed: u64) -> DatabendRng {
+    DatabendRng::seed_from_u64(seed)
+}
@@ -0,0 +1,279 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::batch::{self, BatchReport, Outcome};
+use super::effect::{ParamRanges, ParamValues};
+use super::error::DatabendError;
+use super::progress::Progress;
+use super::rng;
+
+/// How seeds change from one frame to the next.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SeedMode {
+    /// Every frame uses the same seed, so the glitch holds still.
+    #[default]
+    Stable,
+    /// Frame `n` uses seed + `n`, so the glitch flickers.
+    Flicker,
+}
+
+impl SeedMode {
+    pub fn parse(input: &str) -> Option<Self> {
+        match input {
+            "stable" => Some(SeedMode::Stable),
+            "flicker" => Some(SeedMode::Flicker),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SeedMode::Stable => "stable",
+            SeedMode::Flicker => "flicker",
+        }
+    }
+
+    fn seed(self, base: u64, frame: u64) -> u64 {
+        match self {
+            SeedMode::Stable => base,
+            SeedMode::Flicker => base.wrapping_add(frame),
+        }
+    }
+}
+
+/// A numbered file name such as `frames/frame_%05d.png`.
+#[derive(Debug, Clone)]
+pub struct FramePattern {
+    pattern: String,
+    dir: PathBuf,
+    prefix: String,
+    /// Zero-padded width of the number, 0 for no padding.
+    width: usize,
+    suffix: String,
+}
+
+impl FramePattern {
+    /// Parses a path whose file name holds exactly one `%d` or `%0Nd`.
+    pub fn parse(pattern: &str) -> Result<Self, DatabendError> {
+        let invalid = || {
+            DatabendError::InvalidPath(format!(
+                "'{}' needs one %d or %0Nd (like frame_%05d.png) in the file name",
+                pattern
+            ))
+        };
+        let path = Path::new(pattern);
+        let name = path.file_name().ok_or_else(invalid)?.to_string_lossy();
+        let (prefix, rest) = name.split_once('%').ok_or_else(invalid)?;
+        let (spec, suffix) = rest.split_once('d').ok_or_else(invalid)?;
+        if suffix.contains('%') || !spec.chars().all(|c| c.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        Ok(Self {
+            pattern: pattern.to_string(),
+            dir: path
+                .parent()
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .unwrap_or(Path::new("."))
+                .to_path_buf(),
+            prefix: prefix.to_string(),
+            width: spec.parse().unwrap_or(0),
+            suffix: suffix.to_string(),
+        })
+    }
+
+    pub fn path(&self, number: u64) -> PathBuf {
+        self.dir.join(format!(
+            "{}{:0width$}{}",
+            self.prefix,
+            number,
+            self.suffix,
+            width = self.width
+        ))
+    }
+
+    /// The frame number in `name`, if it follows the pattern.
+    fn number(&self, name: &str) -> Option<u64> {
+        let digits = name
+            .strip_prefix(&self.prefix)?
+            .strip_suffix(&self.suffix)?;
+        if digits.len() < self.width.max(1) || !digits.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        digits.parse().ok()
+    }
+
+    /// Every existing frame, as `(number, path)` in frame order.
+    pub fn frames(&self) -> Result<Vec<(u64, PathBuf)>, DatabendError> {
+        if !self.dir.is_dir() {
+            return Err(DatabendError::InvalidPath(format!(
+                "'{}' does not exist",
+                self.dir.display()
+            )));
+        }
+        let mut frames: Vec<_> = fs::read_dir(&self.dir)?
+            .flatten()
+            .filter_map(|entry| {
+                let number = self.number(&entry.file_name().to_string_lossy())?;
+                Some((number, entry.path()))
+            })
+            .collect();
+        if frames.is_empty() {
+            return Err(DatabendError::InvalidPath(format!(
+                "no frames match '{}'",
+                self.pattern
+            )));
+        }
+        frames.sort();
+        Ok(frames)
+    }
+}
+
+/// Whether `input` names a numbered frame sequence rather than one file.
+pub fn is_sequence_input(input: &str) -> bool {
+    Path::new(input)
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().contains('%'))
+}
+
+/// How a sequence went, and the seed it started from.
+#[derive(Debug)]
+pub struct SequenceReport {
+    pub seed: u64,
+    pub frames: BatchReport,
+}
+
+/// Runs `apply` on every frame of `input`, writing the frame with the same
+/// number through `output`: another pattern, or a folder that keeps the
+/// input file names.
+///
+/// Frame `i` of `n` gets the values `i / (n - 1)` of the way through
+/// `ranges`, and a seed derived from `seed` by `seed_mode`. Failing frames
+/// are recorded and skipped; cancelling keeps the frames already written.
+pub fn run(
+    input: &FramePattern,
+    output: &str,
+    ranges: &ParamRanges,
+    seed: Option<u64>,
+    seed_mode: SeedMode,
+    progress: &Progress,
+    mut apply: impl FnMut(&Path, &Path, &ParamValues, u64, &Progress) -> Result<u64, DatabendError>,
+) -> Result<SequenceReport, DatabendError> {
+    let frames = input.frames()?;
+    let output_pattern = if is_sequence_input(output) {
+        Some(FramePattern::parse(output)?)
+    } else {
+        None
+    };
+    fs::create_dir_all(
+        output_pattern
+            .as_ref()
+            .map_or(Path::new(output), |pattern| &pattern.dir),
+    )?;
+
+    let seed = rng::resolve_seed(seed);
+    let mut report = BatchReport::default();
+    let last = frames.len().saturating_sub(1).max(1) as f64;
+    for (index, (number, frame)) in frames.iter().enumerate() {
+        if progress.check().is_err() {
+            report.cancelled = true;
+            break;
+        }
+        let output = match &output_pattern {
+            Some(pattern) => pattern.path(*number),
+            None => Path::new(output).join(frame.file_name().unwrap_or_default()),
+        };
+        let result = if batch::same_file(frame, &output) {
+            Err(DatabendError::InvalidPath(
+                "the output would overwrite the input".to_string(),
+            ))
+        } else {
+            let values = ranges.at(index as f64 / last);
+            apply(
+                frame,
+                &output,
+                &values,
+                seed_mode.seed(seed, *number),
+                &progress.part(index, frames.len()),
+            )
+        };
+        if let Err(DatabendError::Cancelled) = result {
+            report.cancelled = true;
+            break;
+        }
+        report.outcomes.push(Outcome {
+            input: frame.clone(),
+            output,
+            result,
+        });
+    }
+    Ok(SequenceReport {
+        seed,
+        frames: report,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libdatabend::testing::TempDir;
+
+    #[test]
+    fn patterns_need_one_number_in_the_file_name() {
+        let pattern = FramePattern::parse("frames/frame_%05d.png").unwrap();
+        assert_eq!(pattern.path(42), Path::new("frames/frame_00042.png"));
+        assert_eq!(pattern.path(1234567), Path::new("frames/frame_1234567.png"));
+        let bare = FramePattern::parse("%d.tif").unwrap();
+        assert_eq!(bare.path(7), Path::new("./7.tif"));
+
+        for input in [
+            "frame.png",
+            "frame_%05x.png",
+            "%d_%d.png",
+            "f_%-3d.png",
+            "frames/",
+        ] {
+            assert!(
+                matches!(
+                    FramePattern::parse(input),
+                    Err(DatabendError::InvalidPath(_))
+                ),
+                "{:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn names_must_fill_the_padding_to_count_as_frames() {
+        let padded = FramePattern::parse("frame_%03d.png").unwrap();
+        assert_eq!(padded.number("frame_007.png"), Some(7));
+        assert_eq!(padded.number("frame_1000.png"), Some(1000));
+        assert_eq!(padded.number("frame_07.png"), None);
+        assert_eq!(padded.number("frame_0x7.png"), None);
+        assert_eq!(padded.number("frame_007.jpg"), None);
+
+        let bare = FramePattern::parse("frame_%d.png").unwrap();
+        assert_eq!(bare.number("frame_7.png"), Some(7));
+        assert_eq!(bare.number("frame_.png"), None);
+    }
+
+    #[test]
+    fn frames_are_listed_in_number_order() {
+        let dir = TempDir::new("sequence");
+        for name in ["f_10.png", "f_9.png", "f_011.png", "g_1.png", "f_x.png"] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+        let frames = FramePattern::parse(&dir.join("f_%d.png"))
+            .unwrap()
+            .frames()
+            .unwrap();
+        let empty = FramePattern::parse(&dir.join("h_%d.png")).unwrap().frames();
+
+        let numbers: Vec<u64> = frames.iter().map(|(number, _)| *number).collect();
+        assert_eq!(numbers, [9, 10, 11]);
+        assert_eq!(frames[2].1, dir.path().join("f_011.png"));
+        assert!(matches!(empty, Err(DatabendError::InvalidPath(_))));
+    }
+}
@@ -0,0 +1,611 @@
+//! Audio effects run over the pixel buffer, the way images are bent by
+//! importing them into an audio editor as raw 8-bit PCM.
+
+use std::f32::consts::{PI, TAU};
+
+use image::DynamicImage;
+
+use super::effect::{Effect, ParamKind, ParamSpec, ParamValues};
+use super::error::DatabendError;
+use super::imageio;
+use super::progress::Progress;
+use super::rng::DatabendRng;
+
+/// Shared by every filter: run over the interleaved RGBA bytes as one
+/// stream, or over R, G and B as three separate streams. Either way the
+/// output keeps the source alpha.
+const STREAM: ParamSpec = ParamSpec {
+    name: "stream",
+    label: "Stream",
+    kind: ParamKind::Choice(&["interleaved", "channels"]),
+    default: 0.0,
+};
+
+const fn mix(default: f64) -> ParamSpec {
+    ParamSpec {
+        name: "mix",
+        label: "Wet/Dry Mix",
+        kind: ParamKind::Float {
+            min: 0.0,
+            max: 1.0,
+            step: 0.05,
+        },
+        default,
+    }
+}
+
+const fn feedback(default: f64) -> ParamSpec {
+    ParamSpec {
+        name: "feedback",
+        label: "Feedback",
+        kind: ParamKind::Float {
+            min: 0.0,
+            max: 0.99,
+            step: 0.05,
+        },
+        default,
+    }
+}
+
+/// Comb and all-pass delays of the Freeverb reverb at 44.1 kHz.
+const COMB_DELAYS: [f32; 4] = [1557.0, 1617.0, 1491.0, 1422.0];
+const ALLPASS_DELAYS: [f32; 2] = [556.0, 225.0];
+
+/// Range of the phaser's all-pass corner frequency, as a fraction of the
+/// sample rate.
+const PHASER_MIN: f32 = 0.002;
+const PHASER_MAX: f32 = 0.2;
+
+/// Samples a filter pass runs between progress updates.
+const BLOCK: usize = 1 << 16;
+
+pub struct Echo;
+pub struct Reverb;
+pub struct Bitcrush;
+pub struct Lowpass;
+pub struct Phaser;
+pub struct Reverse;
+
+const ECHO_PARAMS: &[ParamSpec] = &[
+    ParamSpec {
+        name: "delay",
+        label: "Delay in samples",
+        kind: ParamKind::Int {
+            min: 1,
+            max: 1_000_000,
+            step: 100,
+        },
+        default: 2000.0,
+    },
+    feedback(0.5),
+    mix(0.5),
+    STREAM,
+];
+
+const REVERB_PARAMS: &[ParamSpec] = &[
+    ParamSpec {
+        name: "room",
+        label: "Room Size",
+        kind: ParamKind::Float {
+            min: 0.1,
+            max: 20.0,
+            step: 0.1,
+        },
+        default: 1.0,
+    },
+    feedback(0.8),
+    mix(0.4),
+    STREAM,
+];
+
+const BITCRUSH_PARAMS: &[ParamSpec] = &[
+    ParamSpec {
+        name: "bits",
+        label: "Bit Depth",
+        kind: ParamKind::Int {
+            min: 1,
+            max: 8,
+            step: 1,
+        },
+        default: 3.0,
+    },
+    ParamSpec {
+        name: "hold",
+        label: "Sample Hold in samples",
+        kind: ParamKind::Int {
+            min: 1,
+            max: 4096,
+            step: 1,
+        },
+        default: 4.0,
+    },
+    mix(1.0),
+    STREAM,
+];
+
+const LOWPASS_PARAMS: &[ParamSpec] = &[
+    ParamSpec {
+        name: "cutoff",
+        label: "Cutoff, fraction of sample rate",
+        kind: ParamKind::Float {
+            min: 0.0001,
+            max: 0.5,
+            step: 0.001,
+        },
+        default: 0.01,
+    },
+    ParamSpec {
+        name: "poles",
+        label: "Poles",
+        kind: ParamKind::Int {
+            min: 1,
+            max: 8,
+            step: 1,
+        },
+        default: 2.0,
+    },
+    mix(1.0),
+    STREAM,
+];
+
+const PHASER_PARAMS: &[ParamSpec] = &[
+    ParamSpec {
+        name: "period",
+        label: "LFO Period in samples",
+        kind: ParamKind::Int {
+            min: 2,
+            max: 10_000_000,
+            step: 1000,
+        },
+        default: 40000.0,
+    },
+    ParamSpec {
+        name: "stages",
+        label: "All-pass Stages",
+        kind: ParamKind::Int {
+            min: 1,
+            max: 24,
+            step: 1,
+        },
+        default: 6.0,
+    },
+    ParamSpec {
+        name: "depth",
+        label: "Depth",
+        kind: ParamKind::Float {
+            min: 0.0,
+            max: 1.0,
+            step: 0.05,
+        },
+        default: 1.0,
+    },
+    feedback(0.6),
+    mix(0.5),
+    STREAM,
+];
+
+const REVERSE_PARAMS: &[ParamSpec] = &[
+    ParamSpec {
+        name: "block",
+        label: "Block Size in samples, 0 = whole stream",
+        kind: ParamKind::Int {
+            min: 0,
+            max: i64::MAX,
+            step: 64,
+        },
+        default: 0.0,
+    },
+    STREAM,
+];
+
+impl Effect for Echo {
+    fn name(&self) -> &'static str {
+        "echo"
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        ECHO_PARAMS
+    }
+
+    fn apply(
+        &self,
+        img: &DynamicImage,
+        params: &ParamValues,
+        _rng: &mut DatabendRng,
+        progress: &Progress,
+    ) -> Result<DynamicImage, DatabendError> {
+        let delay = params.int("delay") as usize;
+        let feedback = params.float("feedback") as f32;
+        let mix = params.float("mix") as f32;
+        sonify(img, params, progress, |samples, progress| {
+            let wet = comb(samples, delay, feedback, progress)?;
+            blend(samples, &wet, mix);
+            Ok(())
+        })
+    }
+}
+
+impl Effect for Reverb {
+    fn name(&self) -> &'static str {
+        "reverb"
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        REVERB_PARAMS
+    }
+
+    fn apply(
+        &self,
+        img: &DynamicImage,
+        params: &ParamValues,
+        _rng: &mut DatabendRng,
+        progress: &Progress,
+    ) -> Result<DynamicImage, DatabendError> {
+        let room = params.float("room") as f32;
+        let feedback = params.float("feedback") as f32;
+        let mix = params.float("mix") as f32;
+        sonify(img, params, progress, |samples, progress| {
+            // Schroeder reverb: parallel combs into series all-passes
+            let passes = COMB_DELAYS.len() + ALLPASS_DELAYS.len();
+            let mut wet = vec![0.0; samples.len()];
+            for (pass, delay) in COMB_DELAYS.into_iter().enumerate() {
+                let delay = ((delay * room) as usize).max(1);
+                let comb = comb(samples, delay, feedback, &progress.part(pass, passes))?;
+                for (wet, comb) in wet.iter_mut().zip(comb) {
+                    *wet += comb / COMB_DELAYS.len() as f32;
+                }
+            }
+            for (pass, delay) in ALLPASS_DELAYS.into_iter().enumerate() {
+                let delay = ((delay * room) as usize).max(1);
+                let progress = progress.part(COMB_DELAYS.len() + pass, passes);
+                wet = allpass(&wet, delay, 0.5, &progress)?;
+            }
+            blend(samples, &wet, mix);
+            Ok(())
+        })
+    }
+}
+
+impl Effect for Bitcrush {
+    fn name(&self) -> &'static str {
+        "bitcrush"
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        BITCRUSH_PARAMS
+    }
+
+    fn apply(
+        &self,
+        img: &DynamicImage,
+        params: &ParamValues,
+        _rng: &mut DatabendRng,
+        progress: &Progress,
+    ) -> Result<DynamicImage, DatabendError> {
+        let bits = params.int("bits");
+        let hold = params.int("hold") as usize;
+        let mix = params.float("mix") as f32;
+        let steps = (1 << (bits - 1)) as f32;
+        sonify(img, params, progress, |samples, _progress| {
+            let wet: Vec<f32> = samples
+                .chunks(hold)
+                .flat_map(|run| {
+                    let held = (run[0] * steps).round() / steps;
+                    std::iter::repeat_n(held, run.len())
+                })
+                .collect();
+            blend(samples, &wet, mix);
+            Ok(())
+        })
+    }
+}
+
+impl Effect for Lowpass {
+    fn name(&self) -> &'static str {
+        "lowpass"
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        LOWPASS_PARAMS
+    }
+
+    fn apply(
+        &self,
+        img: &DynamicImage,
+        params: &ParamValues,
+        _rng: &mut DatabendRng,
+        progress: &Progress,
+    ) -> Result<DynamicImage, DatabendError> {
+        let cutoff = params.float("cutoff") as f32;
+        let poles = params.int("poles");
+        let mix = params.float("mix") as f32;
+        // One-pole smoothing coefficient for the cutoff frequency
+        let alpha = 1.0 - (-TAU * cutoff).exp();
+        sonify(img, params, progress, |samples, progress| {
+            let mut wet = samples.to_vec();
+            let total = wet.len();
+            for pole in 0..poles as usize {
+                let progress = progress.part(pole, poles as usize);
+                let mut state = wet.first().copied().unwrap_or_default();
+                for (n, sample) in wet.iter_mut().enumerate() {
+                    report_block(&progress, n, total)?;
+                    state += alpha * (*sample - state);
+                    *sample = state;
+                }
+            }
+            blend(samples, &wet, mix);
+            Ok(())
+        })
+    }
+}
+
+impl Effect for Phaser {
+    fn name(&self) -> &'static str {
+        "phaser"
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        PHASER_PARAMS
+    }
+
+    fn apply(
+        &self,
+        img: &DynamicImage,
+        params: &ParamValues,
+        _rng: &mut DatabendRng,
+        progress: &Progress,
+    ) -> Result<DynamicImage, DatabendError> {
+        let period = params.int("period") as f32;
+        let stages = params.int("stages") as usize;
+        let depth = params.float("depth") as f32;
+        let feedback = params.float("feedback") as f32;
+        let mix = params.float("mix") as f32;
+        sonify(img, params, progress, |samples, progress| {
+            // Previous input and output of each first-order all-pass stage
+            let mut state = vec![(0.0f32, 0.0f32); stages];
+            let mut last = 0.0;
+            let mut wet = Vec::with_capacity(samples.len());
+            for (n, &sample) in samples.iter().enumerate() {
+                report_block(progress, n, samples.len())?;
+                let lfo = 0.5 + 0.5 * (TAU * n as f32 / period).sin();
+                let corner = PHASER_MIN + (PHASER_MAX - PHASER_MIN) * depth * lfo;
+                let t = (PI * corner).tan();
+                let a = (t - 1.0) / (t + 1.0);
+
+                let mut x = sample + feedback * last;
+                for (x1, y1) in state.iter_mut() {
+                    let y = a * x + *x1 - a * *y1;
+                    *x1 = x;
+                    *y1 = y;
+                    x = y;
+                }
+                last = x.clamp(-1.0, 1.0);
+                wet.push(x);
+            }
+            blend(samples, &wet, mix);
+            Ok(())
+        })
+    }
+}
+
+impl Effect for Reverse {
+    fn name(&self) -> &'static str {
+        "reverse"
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        REVERSE_PARAMS
+    }
+
+    fn apply(
+        &self,
+        img: &DynamicImage,
+        params: &ParamValues,
+        _rng: &mut DatabendRng,
+        progress: &Progress,
+    ) -> Result<DynamicImage, DatabendError> {
+        let block = params.int("block") as usize;
+        sonify(img, params, progress, |samples, _progress| {
+            if block == 0 {
+                samples.reverse();
+            } else {
+                samples.chunks_mut(block).for_each(<[f32]>::reverse);
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Converts the image to RGBA8 samples, runs `filter` over them as
+/// configured by the `stream` param and rebuilds the image. Each stream gets
+/// its share of `progress`, which the filter reports into per pass or block.
+fn sonify(
+    img: &DynamicImage,
+    params: &ParamValues,
+    progress: &Progress,
+    filter: impl Fn(&mut [f32], &Progress) -> Result<(), DatabendError>,
+) -> Result<DynamicImage, DatabendError> {
+    let mut rawimg = img.to_rgba8().into_raw();
+
+    if params.int("stream") == 1 {
+        // Alpha is left alone so separate channels never change transparency
+        for channel in 0..3 {
+            let progress = progress.part(channel, 3);
+            let mut samples: Vec<f32> = rawimg
+                .iter()
+                .skip(channel)
+                .step_by(4)
+                .map(|&byte| to_sample(byte))
+                .collect();
+            filter(&mut samples, &progress)?;
+            let bytes = rawimg.iter_mut().skip(channel).step_by(4);
+            for (byte, sample) in bytes.zip(samples) {
+                *byte = from_sample(sample);
+            }
+            progress.update(1, 1)?;
+        }
+    } else {
+        let mut samples: Vec<f32> = rawimg.iter().map(|&byte| to_sample(byte)).collect();
+        filter(&mut samples, progress)?;
+        // Alpha bleeds into the colours but keeps its source value, as above
+        for (pixel, samples) in rawimg.chunks_exact_mut(4).zip(samples.chunks_exact(4)) {
+            for (byte, &sample) in pixel.iter_mut().zip(samples).take(3) {
+                *byte = from_sample(sample);
+            }
+        }
+        progress.update(1, 1)?;
+    }
+
+    imageio::from_rgba(img.width(), img.height(), rawimg, img.color().has_alpha())
+}
+
+/// Reads a byte as unsigned 8-bit PCM, centred on 128.
+fn to_sample(byte: u8) -> f32 {
+    (byte as f32 - 128.0) / 128.0
+}
+
+/// Writes a sample back as unsigned 8-bit PCM, hard clipping it.
+fn from_sample(sample: f32) -> u8 {
+    (sample * 128.0 + 128.0).round().clamp(0.0, 255.0) as u8
+}
+
+/// Reports how far a pass over `total` samples has got, once every
+/// [`BLOCK`] samples so the check stays out of the inner loop's way.
+fn report_block(progress: &Progress, n: usize, total: usize) -> Result<(), DatabendError> {
+    if n.is_multiple_of(BLOCK) {
+        progress.update(n, total)
+    } else {
+        Ok(())
+    }
+}
+
+/// Feedback comb filter: `y[n] = x[n] + feedback * y[n - delay]`.
+fn comb(
+    samples: &[f32],
+    delay: usize,
+    feedback: f32,
+    progress: &Progress,
+) -> Result<Vec<f32>, DatabendError> {
+    let mut out = samples.to_vec();
+    for n in delay..out.len() {
+        report_block(progress, n, samples.len())?;
+        out[n] += feedback * out[n - delay];
+    }
+    Ok(out)
+}
+
+/// Schroeder all-pass: `y[n] = -g * x[n] + x[n - delay] + g * y[n - delay]`.
+fn allpass(
+    samples: &[f32],
+    delay: usize,
+    gain: f32,
+    progress: &Progress,
+) -> Result<Vec<f32>, DatabendError> {
+    let mut out = vec![0.0; samples.len()];
+    for n in 0..samples.len() {
+        report_block(progress, n, samples.len())?;
+        out[n] = -gain * samples[n];
+        if n >= delay {
+            out[n] += samples[n - delay] + gain * out[n - delay];
+        }
+    }
+    Ok(out)
+}
+
+/// Mixes `wet` into `samples`: 0.0 keeps the dry signal, 1.0 replaces it.
+fn blend(samples: &mut [f32], wet: &[f32], mix: f32) {
+    for (dry, wet) in samples.iter_mut().zip(wet) {
+        *dry = *dry * (1.0 - mix) + wet * mix;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::sync::atomic::AtomicBool;
+
+    use image::RgbaImage;
+
+    use super::*;
+    use crate::libdatabend::rng;
+
+    fn run(
+        effect: &dyn Effect,
+        stream: i64,
+        cancel: bool,
+    ) -> (Vec<f32>, Result<(), DatabendError>) {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(300, 300, |x, y| {
+            image::Rgba([x as u8, y as u8, (x ^ y) as u8, 255])
+        }));
+        let mut params = ParamValues::defaults(effect.params());
+        params.set(
+            "stream",
+            crate::libdatabend::effect::ParamValue::Int(stream),
+        );
+        let reports = RefCell::new(Vec::new());
+        let report = |fraction| reports.borrow_mut().push(fraction);
+        let cancel = AtomicBool::new(cancel);
+        let progress = Progress::new(&report, &cancel);
+        let result = effect.apply(&img, &params, &mut rng::from_seed(1), &progress);
+        (reports.into_inner(), result.map(|_| ()))
+    }
+
+    #[test]
+    fn every_filter_reports_progress_within_a_stream() {
+        let effects: [&dyn Effect; 6] = [&Echo, &Reverb, &Bitcrush, &Lowpass, &Phaser, &Reverse];
+        for effect in effects {
+            for stream in [0, 1] {
+                let (reports, result) = run(effect, stream, false);
+                result.unwrap();
+                assert!(
+                    reports.windows(2).all(|pair| pair[0] <= pair[1]),
+                    "{}",
+                    effect.name()
+                );
+                assert_eq!(reports.last(), Some(&1.0), "{}", effect.name());
+            }
+        }
+        // 360,000 interleaved samples span several blocks of a single pass
+        let (reports, _) = run(&Phaser, 0, false);
+        assert!(reports.len() > 3);
+        let (reports, _) = run(&Reverb, 0, false);
+        assert!(reports.len() > 6 * 3);
+    }
+
+    #[test]
+    fn interleaved_filters_stop_when_cancelled() {
+        for effect in [&Echo as &dyn Effect, &Reverb, &Lowpass, &Phaser] {
+            let (reports, result) = run(effect, 0, true);
+            assert!(
+                matches!(result, Err(DatabendError::Cancelled)),
+                "{}",
+                effect.name()
+            );
+            assert!(reports.iter().all(|&fraction| fraction < 1.0));
+        }
+    }
+
+    #[test]
+    fn both_streams_keep_the_source_alpha() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(64, 64, |x, y| {
+            image::Rgba([x as u8 * 4, y as u8 * 4, 200, (x * 3 + y) as u8])
+        }));
+        let effects: [&dyn Effect; 6] = [&Echo, &Reverb, &Bitcrush, &Lowpass, &Phaser, &Reverse];
+        for effect in effects {
+            for stream in [0, 1] {
+                let mut params = ParamValues::defaults(effect.params());
+                params.set(
+                    "stream",
+                    crate::libdatabend::effect::ParamValue::Int(stream),
+                );
+                let out = effect
+                    .apply(&img, &params, &mut rng::from_seed(1), &Progress::none())
+                    .unwrap()
+                    .to_rgba8();
+                let alpha = |image: &RgbaImage| image.pixels().map(|p| p[3]).collect::<Vec<_>>();
+                assert_eq!(alpha(&out), alpha(&img.to_rgba8()), "{}", effect.name());
+                assert_ne!(out, img.to_rgba8(), "{}", effect.name());
+            }
+        }
+    }
+}
@@ -1,75 +1,106 @@
-use image::{DynamicImage, ImageBuffer, ImageReader, RgbImage};
-use rand::{Rng, rng};
+use rand::Rng;
+
+use super::effect::{ParamKind, ParamSpec, ParamValues};
+use super::error::DatabendError;
+use super::history::Edit;
+use super::interactive::{Canvas, InteractiveState};
+use super::rng::{self, DatabendRng};
+
+/// The name recipes, key logs and the command line use for this effect.
+pub const ID: &str = "synesthesia";
+
+/// Each key bends `(key * 13) % spread + base` bytes.
+pub const PARAMS: &[ParamSpec] = &[
+    ParamSpec {
+        name: "spread",
+        label: "Chaos Spread between keys",
+        kind: ParamKind::Int {
+            min: 1,
+            max: 100_000,
+            step: 50,
+        },
+        default: 500.0,
+    },
+    ParamSpec {
+        name: "base",
+        label: "Chaos Base per key",
+        kind: ParamKind::Int {
+            min: 0,
+            max: 100_000,
+            step: 10,
+        },
+        default: 50.0,
+    },
+];
 
 pub struct SynesthesiaState {
-    pub rawimg: Vec<u8>,
-    pub rng: rand::rngs::ThreadRng,
-    pub modifications_count: usize,
-    pub img_width: u32,
-    pub img_height: u32,
+    pub canvas: Canvas,
+    pub rng: DatabendRng,
+    pub seed: u64,
+    spread: usize,
+    base: usize,
 }
 
 impl SynesthesiaState {
-    pub fn new(input_path: &str) -> Result<Self, String> {
-        let img = ImageReader::open(input_path)
-            .map_err(|e| format!("Failed to open image: {}", e))?;
-        
-        let img = img.decode()
-            .map_err(|e| format!("Failed to decode image: {}", e))?;
-
-        let rawimg = img.to_rgba8().into_raw();
-        
+    pub fn new(
+        input_path: &str,
+        seed: Option<u64>,
+        params: &ParamValues,
+    ) -> Result<Self, DatabendError> {
+        params.check(PARAMS)?;
+        let seed = rng::resolve_seed(seed);
         Ok(Self {
-            rawimg,
-            rng: rng(),
-            modifications_count: 0,
-            img_width: img.width(),
-            img_height: img.height(),
+            canvas: Canvas::load(input_path)?,
+            rng: rng::from_seed(seed),
+            seed,
+            spread: params.int("spread") as usize,
+            base: params.int("base") as usize,
         })
     }
+}
+
+impl InteractiveState for SynesthesiaState {
+    fn canvas(&self) -> &Canvas {
+        &self.canvas
+    }
+
+    fn canvas_mut(&mut self) -> &mut Canvas {
+        &mut self.canvas
+    }
 
-    pub fn process_key(&mut self, c: char) -> String {
+    fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn process_key(&mut self, c: char) -> String {
         let value = c as u8;
-        let chaos_amount = (value as usize * 13) % 500 + 50;
-        
+        let chaos_amount = (value as usize * 13) % self.spread + self.base;
+        let rawimg = &mut self.canvas.rawimg;
+        let mut edit = Edit::default();
+
         for _ in 0..chaos_amount {
-            let random_index = self.rng.random_range(0..self.rawimg.len());
-            
-            match c {
-                'a'..='z' => {
-                    self.rawimg[random_index] = self.rawimg[random_index].wrapping_add(value);
-                }
-                '0'..='9' => {
-                    self.rawimg[random_index] = value.wrapping_mul(17);
-                }
-                ' ' => {
-                    self.rawimg[random_index] = 0;
-                }
-                _ => {
-                    self.rawimg[random_index] = self.rng.random_range(0..=255);
-                }
-            }
-        }
-        
-        self.modifications_count += chaos_amount;
-        format!("🎵 Key '{}' pressed - {} pixels databent! (Total: {})", c, chaos_amount, self.modifications_count)
-    }
+            let random_index = self.rng.random_range(0..rawimg.len());
 
-    pub fn save(&self, output_path: &str) -> Result<String, String> {
-        let rgb_data: Vec<u8> = self.rawimg
-            .chunks(4)
-            .flat_map(|rgba| &rgba[..3])
-            .copied()
-            .collect();
+            let new_value = match c {
+                'a'..='z' => rawimg[random_index].wrapping_add(value),
+                '0'..='9' => value.wrapping_mul(17),
+                ' ' => 0,
+                _ => self.rng.random_range(0..=255),
+            };
+            edit.set(rawimg, random_index, new_value);
+        }
 
-        let new_img: RgbImage = ImageBuffer::from_raw(self.img_width, self.img_height, rgb_data)
-            .ok_or("Failed to create new image")?;
-        
-        DynamicImage::ImageRgb8(new_img)
-            .save(output_path)
-            .map_err(|e| format!("Failed to save image: {}", e))?;
+        self.canvas.commit(edit);
+        format!(
+            "🎵 Key '{}' pressed - {} pixels databent! (Total: {})",
+            c, chaos_amount, self.canvas.modifications_count
+        )
+    }
 
-        Ok(format!("🎭 Synesthesia complete! {} pixels modified total", self.modifications_count))
+    fn finish_message(&self) -> String {
+        format!(
+            "🎭 Synesthesia complete! {} pixels modified total (seed {})",
+            self.canvas.modifications_count, self.seed
+        )
     }
 }
-
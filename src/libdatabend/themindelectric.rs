@@ -1,60 +1,146 @@
-use image::{DynamicImage, GenericImageView, ImageBuffer, ImageReader, Rgba, RgbImage};
-use rand::{Rng, rng};
-use std::error::Error;
-
-pub fn main(input_path: &str, output_path: &str, layers: &u32) -> Result<(), Box<dyn Error>> {
-    let img = ImageReader::open(input_path)?.decode()?;
-    let mut rng = rng();
-    let (width, height) = (img.width(), img.height());
-    let mut canvas = ImageBuffer::from_pixel(width, height, Rgba([0, 0, 0, 0]));
-
-    for _ in 0..*layers {
-        let offset_x = rng.random_range(0..width);
-        let offset_y = rng.random_range(0..height);
-        let alpha_mult = rng.random_range(0.1..0.3);
-
-        let color_shift = (
-            rng.random_range(0..256),
-            rng.random_range(0..256),
-            rng.random_range(0..256),
-        );
-
-        for y in 0..height {
-            for x in 0..width {
-                let src_x = x as i32 - offset_x as i32;
-                let src_y = y as i32 - offset_y as i32;
-
-                if src_x >= 0 && src_x < width as i32 && src_y >= 0 && src_y < height as i32 {
-                    let pixel = img.get_pixel(src_x as u32, src_y as u32);
-
-                    let r = (pixel[0] as i32 + color_shift.0).clamp(0, 255) as u8;
-                    let g = (pixel[1] as i32 + color_shift.1).clamp(0, 255) as u8;
-                    let b = (pixel[2] as i32 + color_shift.2).clamp(0, 255) as u8;
-                    let a = (pixel[3] as f32 * alpha_mult) as u8;
+use image::{DynamicImage, GenericImageView, ImageBuffer, Rgba};
+use rand::Rng;
+
+use super::effect::{Effect, ParamKind, ParamSpec, ParamValues};
+use super::error::DatabendError;
+use super::progress::Progress;
+use super::rng::DatabendRng;
+
+pub struct TheMindElectric;
+
+const PARAMS: &[ParamSpec] = &[
+    ParamSpec {
+        name: "layers",
+        label: "Layers",
+        kind: ParamKind::Int {
+            min: 1,
+            max: 20,
+            step: 1,
+        },
+        default: 5.0,
+    },
+    ParamSpec {
+        name: "alpha_min",
+        label: "Layer Opacity, lowest",
+        kind: ParamKind::Float {
+            min: 0.0,
+            max: 1.0,
+            step: 0.05,
+        },
+        default: 0.1,
+    },
+    ParamSpec {
+        name: "alpha_max",
+        label: "Layer Opacity, highest",
+        kind: ParamKind::Float {
+            min: 0.0,
+            max: 1.0,
+            step: 0.05,
+        },
+        default: 0.3,
+    },
+    ParamSpec {
+        name: "shift_min",
+        label: "Colour Shift, lowest",
+        kind: ParamKind::Int {
+            min: -255,
+            max: 255,
+            step: 5,
+        },
+        default: 0.0,
+    },
+    ParamSpec {
+        name: "shift_max",
+        label: "Colour Shift, highest",
+        kind: ParamKind::Int {
+            min: -255,
+            max: 255,
+            step: 5,
+        },
+        default: 255.0,
+    },
+];
+
+impl Effect for TheMindElectric {
+    fn name(&self) -> &'static str {
+        "themindelectric"
+    }
 
-                    let new_pixel = Rgba([r, g, b, a]);
+    fn params(&self) -> &'static [ParamSpec] {
+        PARAMS
+    }
 
-                    let dst_pixel = canvas.get_pixel_mut(x, y);
-                    *dst_pixel = alpha_blend(*dst_pixel, new_pixel);
+    fn apply(
+        &self,
+        img: &DynamicImage,
+        params: &ParamValues,
+        rng: &mut DatabendRng,
+        progress: &Progress,
+    ) -> Result<DynamicImage, DatabendError> {
+        let layers = params.int("layers").max(0) as u32;
+        let (alpha_min, alpha_max) = (params.float("alpha_min"), params.float("alpha_max"));
+        let (shift_min, shift_max) = (
+            params.int("shift_min") as i32,
+            params.int("shift_max") as i32,
+        );
+        if alpha_min > alpha_max {
+            return Err(DatabendError::invalid_parameter(
+                "alpha_min",
+                "must not be above alpha_max",
+            ));
+        }
+        if shift_min > shift_max {
+            return Err(DatabendError::invalid_parameter(
+                "shift_min",
+                "must not be above shift_max",
+            ));
+        }
+        let (width, height) = (img.width(), img.height());
+        let mut canvas = ImageBuffer::from_pixel(width, height, Rgba([0, 0, 0, 0]));
+
+        for layer in 0..layers {
+            let offset_x = rng.random_range(0..width);
+            let offset_y = rng.random_range(0..height);
+            let alpha_mult = if alpha_min < alpha_max {
+                rng.random_range(alpha_min as f32..alpha_max as f32)
+            } else {
+                alpha_min as f32
+            };
+
+            let color_shift = (
+                rng.random_range(shift_min..=shift_max),
+                rng.random_range(shift_min..=shift_max),
+                rng.random_range(shift_min..=shift_max),
+            );
+
+            for y in 0..height {
+                for x in 0..width {
+                    let src_x = x as i32 - offset_x as i32;
+                    let src_y = y as i32 - offset_y as i32;
+
+                    if src_x >= 0 && src_x < width as i32 && src_y >= 0 && src_y < height as i32 {
+                        let pixel = img.get_pixel(src_x as u32, src_y as u32);
+
+                        let r = (pixel[0] as i32 + color_shift.0).clamp(0, 255) as u8;
+                        let g = (pixel[1] as i32 + color_shift.1).clamp(0, 255) as u8;
+                        let b = (pixel[2] as i32 + color_shift.2).clamp(0, 255) as u8;
+                        let a = (pixel[3] as f32 * alpha_mult) as u8;
+
+                        let new_pixel = Rgba([r, g, b, a]);
+
+                        let dst_pixel = canvas.get_pixel_mut(x, y);
+                        *dst_pixel = alpha_blend(*dst_pixel, new_pixel);
+                    }
                 }
+                progress.update(
+                    (layer * height + y + 1) as usize,
+                    (layers * height) as usize,
+                )?;
             }
         }
-    }
-
-    let rgb_data: Vec<u8> = canvas
-        .chunks(4)
-        .flat_map(|rgba| &rgba[..3])
-        .copied()
-        .collect();
 
-    let new_img: RgbImage = ImageBuffer::from_raw(width, height, rgb_data)
-        .ok_or("Failed to create new image")?;
-
-    DynamicImage::ImageRgb8(new_img)
-        .save(output_path)
-        .map_err(|e| format!("Failed to save image: {}", e))?;
-
-    Ok(())
+        Ok(DynamicImage::ImageRgba8(canvas))
+    }
 }
 
 fn alpha_blend(bottom: Rgba<u8>, top: Rgba<u8>) -> Rgba<u8> {
@@ -1,7 +1,28 @@
+mod browser;
+mod cli;
 mod libdatabend;
+mod preview;
+
+use browser::{BrowseTarget, FileBrowser};
+use libdatabend::batch::{self, BatchReport};
+use libdatabend::effect::{Effect, ParamRanges, ParamSpec, ParamValues};
+use libdatabend::error::DatabendError;
+use libdatabend::filebend::{self, Bent, FileBender};
+use libdatabend::imageio::{self, SaveOptions};
+use libdatabend::interactive::InteractiveState;
+use libdatabend::keylog::{KeyLog, KeyStroke};
+use libdatabend::pipeline::{Pipeline, PipelineStep};
+use libdatabend::progress::Progress;
+use libdatabend::recipe::{Recipe, RecipeStep};
+use libdatabend::recording::{self, AnimationFormat, Recorder, SessionSettings};
+use libdatabend::region::Region;
+use libdatabend::sequence::{self, FramePattern, SeedMode, SequenceReport};
+use preview::Preview;
 
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers,
+    },
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
@@ -14,19 +35,88 @@ use ratatui::{
     widgets::{Block, BorderType, Borders, Clear, Gauge, List, ListItem, Paragraph, Wrap},
 };
 use std::{
+    cell::Cell,
     io::{self, Stdout},
-    path::Path,
+    panic,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+    },
+    thread,
     time::{Duration, Instant},
 };
 
 type Tui = Terminal<CrosstermBackend<Stdout>>;
 
-#[derive(Debug, Clone)]
+/// Minimum time between live preview refreshes during interactive effects.
+const LIVE_PREVIEW_INTERVAL: Duration = Duration::from_millis(120);
+
+#[derive(Clone)]
 struct DatabendEffect {
     name: String,
     description: String,
     emoji: String,
+    /// Typed descriptors for the parameter editor; empty for effects without any.
+    params: &'static [ParamSpec],
+    /// The in-memory effect behind this entry; `None` for interactive effects.
+    effect: Option<&'static dyn Effect>,
+    /// The file-level bender behind this entry, if it works on encoded bytes.
+    bender: Option<&'static dyn FileBender>,
+    /// The name of the interactive session behind this entry, as
+    /// `libdatabend::start_session` knows it.
+    session: Option<&'static str>,
+}
+
+impl DatabendEffect {
+    /// The name recipes, key logs and the command line use for this entry.
+    fn id(&self) -> &'static str {
+        match (self.effect, self.bender, self.session) {
+            (Some(effect), _, _) => effect.name(),
+            (None, Some(bender), _) => bender.name(),
+            (None, None, session) => session.unwrap_or_default(),
+        }
+    }
+}
+
+/// What the worker thread sends back while an effect runs.
+enum JobMessage {
+    /// Fraction of the work done, in `0.0..=1.0`.
+    Progress(f32),
+    /// What the run produced, or why it failed.
+    Finished(Result<JobOutput, DatabendError>),
+}
+
+/// The result of a worker run that went through.
+enum JobOutput {
+    /// One output file, written with this seed.
+    Seed(u64),
+    /// One bent file, which may be missing the decoded copy it asked for.
+    Bent(Bent),
+    /// A batch, with the fate of every image.
+    Batch(BatchReport),
+    /// A frame sequence, with its base seed and the fate of every frame.
+    Sequence(SequenceReport),
+    /// A recorded session animation, with its frame count.
+    Recording { path: String, frames: usize },
+}
+
+/// An effect, bender or chain running on the worker thread.
+struct Job {
+    receiver: Receiver<JobMessage>,
+    /// Number of enabled steps when running the chain.
+    chain_steps: Option<usize>,
+    /// Set to ask the worker to stop at its next progress update.
+    cancel: Arc<AtomicBool>,
+}
+
+/// One step of the effect chain, pointing into `App.effects`.
+#[derive(Debug, Clone)]
+struct ChainStep {
+    effect: usize,
     params: Vec<String>,
+    enabled: bool,
 }
 
 struct App {
@@ -35,12 +125,45 @@ struct App {
     input_path: String,
     output_path: String,
     params: Vec<String>,
+    seed: String,
+    background: String,
+    /// Rectangles or a mask image the effect is kept to; empty means the
+    /// whole image.
+    region: String,
+    /// Pixels the region's edge fades over; empty means a hard edge.
+    feather: String,
+    /// Output file name template for batches; empty means the default.
+    template: String,
+    /// How seeds change across the frames of a sequence.
+    seed_mode: SeedMode,
+    /// The recipe file last loaded or saved.
+    recipe_path: String,
+    /// The recording popup's fields, one per `recording::SETTINGS`.
+    recording: Vec<String>,
+    pipeline: Vec<ChainStep>,
+    selected_step: usize,
     current_input: InputMode,
     processing: bool,
     progress: f64,
     status_message: String,
     last_update: Instant,
-    synesthesia_state: Option<libdatabend::synestesia::SynesthesiaState>,
+    /// The running interactive effect, if any.
+    session: Option<Box<dyn InteractiveState>>,
+    /// How the running interactive session is saved, resolved when it started.
+    session_options: SaveOptions,
+    /// The file-to-file run in progress on the worker thread, if any.
+    job: Option<Job>,
+    /// Frames captured from the running interactive session, if recording.
+    recorder: Option<Recorder>,
+    /// Every key of the running interactive session, if it keeps a key log.
+    key_log: Option<KeyLog>,
+    /// The file browser popup while picking a path.
+    browser: Option<FileBrowser>,
+    preview: Option<Preview>,
+    truecolor: bool,
+    /// The interactive buffer changed since the preview was last rebuilt.
+    preview_dirty: bool,
+    last_preview: Instant,
 }
 
 #[derive(Debug, PartialEq)]
@@ -48,10 +171,29 @@ enum InputMode {
     SelectingEffect,
     InputPath,
     OutputPath,
+    Seed,
+    Background,
+    Region,
+    Feather,
+    Template,
     Parameters(usize),
+    Recording(usize),
+    Pipeline,
+    Browsing,
+    Recipe(RecipeAction),
     Processing,
 }
 
+/// What the recipe popup does with its path.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RecipeAction {
+    Load,
+    /// Save the selected effect and its parameters.
+    SaveEffect,
+    /// Save the enabled steps of the chain.
+    SaveChain,
+}
+
 impl App {
     fn new() -> Self {
         let effects = vec![
@@ -60,44 +202,154 @@ impl App {
                 description: "Simulates high ISO sensitivity with random noise corruption"
                     .to_string(),
                 emoji: "📸".to_string(),
-                params: vec!["ISO (0-6400)".to_string()],
+                params: libdatabend::oversensibility::Oversensibility.params(),
+                effect: Some(&libdatabend::oversensibility::Oversensibility),
+                bender: None,
+                session: None,
             },
             DatabendEffect {
                 name: "Overexposure".to_string(),
                 description: "Creates blown-out highlights with random brightness boosts"
                     .to_string(),
                 emoji: "☀️".to_string(),
-                params: vec!["Exposure Factor (0.1-3.0)".to_string()],
+                params: libdatabend::overexposure::Overexposure.params(),
+                effect: Some(&libdatabend::overexposure::Overexposure),
+                bender: None,
+                session: None,
             },
             DatabendEffect {
                 name: "Synesthesia".to_string(),
                 description: "Interactive databending - press keys to bend reality".to_string(),
                 emoji: "🎹".to_string(),
-                params: vec![],
+                params: libdatabend::synestesia::PARAMS,
+                effect: None,
+                bender: None,
+                session: Some(libdatabend::synestesia::ID),
             },
             DatabendEffect {
                 name: "Variations on a Cloud".to_string(),
                 description: "Creates glitchy patches by copying random image regions".to_string(),
                 emoji: "☁️".to_string(),
-                params: vec!["Patch Size (10-200)".to_string()],
+                params: libdatabend::variationsonacloud::VariationsOnACloud.params(),
+                effect: Some(&libdatabend::variationsonacloud::VariationsOnACloud),
+                bender: None,
+                session: None,
             },
             DatabendEffect {
                 name: "The Mind Electric".to_string(),
                 description: "Layered chaos with alpha blending and color shifts".to_string(),
                 emoji: "⚡".to_string(),
-                params: vec!["Layers (1-20)".to_string()],
+                params: libdatabend::themindelectric::TheMindElectric.params(),
+                effect: Some(&libdatabend::themindelectric::TheMindElectric),
+                bender: None,
+                session: None,
             },
             DatabendEffect {
                 name: "Jack Stauberism".to_string(),
                 description: "Lyrical databending with song lyrics as corruption data".to_string(),
                 emoji: "🎵".to_string(),
-                params: vec![],
+                params: &[],
+                effect: None,
+                bender: None,
+                session: Some(libdatabend::jackstauberism::ID),
             },
             DatabendEffect {
                 name: "New Normal".to_string(),
                 description: "Interactive chaos mode - embrace the new normal".to_string(),
                 emoji: "🌈".to_string(),
-                params: vec![],
+                params: &[],
+                effect: None,
+                bender: None,
+                session: Some(libdatabend::newnormal::ID),
+            },
+            DatabendEffect {
+                name: "File Bend".to_string(),
+                description: "Corrupts the encoded file bytes for real codec artifacts".to_string(),
+                emoji: "💾".to_string(),
+                params: libdatabend::filebend::ByteBender.params(),
+                effect: None,
+                bender: Some(&libdatabend::filebend::ByteBender),
+                session: None,
+            },
+            DatabendEffect {
+                name: "JPEG Bend".to_string(),
+                description: "Corrupts only JPEG scan data - a guaranteed decodable glitch"
+                    .to_string(),
+                emoji: "📼".to_string(),
+                params: libdatabend::jpegbend::JpegBender.params(),
+                effect: None,
+                bender: Some(&libdatabend::jpegbend::JpegBender),
+                session: None,
+            },
+            DatabendEffect {
+                name: "PNG Bend".to_string(),
+                description: "Corrupts PNG scanlines or IDAT data and repairs the chunks"
+                    .to_string(),
+                emoji: "🧵".to_string(),
+                params: libdatabend::pngbend::PngBender.params(),
+                effect: None,
+                bender: Some(&libdatabend::pngbend::PngBender),
+                session: None,
+            },
+            DatabendEffect {
+                name: "Echo".to_string(),
+                description: "Feeds the pixels back into themselves like an audio delay"
+                    .to_string(),
+                emoji: "🔁".to_string(),
+                params: libdatabend::sonification::Echo.params(),
+                effect: Some(&libdatabend::sonification::Echo),
+                bender: None,
+                session: None,
+            },
+            DatabendEffect {
+                name: "Reverb".to_string(),
+                description:
+                    "Smears the image through a Schroeder reverb, like a bounce through Audacity"
+                        .to_string(),
+                emoji: "🏛️".to_string(),
+                params: libdatabend::sonification::Reverb.params(),
+                effect: Some(&libdatabend::sonification::Reverb),
+                bender: None,
+                session: None,
+            },
+            DatabendEffect {
+                name: "Bitcrush".to_string(),
+                description: "Crushes the pixel stream to fewer bits and a lower sample rate"
+                    .to_string(),
+                emoji: "👾".to_string(),
+                params: libdatabend::sonification::Bitcrush.params(),
+                effect: Some(&libdatabend::sonification::Bitcrush),
+                bender: None,
+                session: None,
+            },
+            DatabendEffect {
+                name: "Low-pass".to_string(),
+                description: "Low-pass filters the pixel stream into soft horizontal streaks"
+                    .to_string(),
+                emoji: "🌊".to_string(),
+                params: libdatabend::sonification::Lowpass.params(),
+                effect: Some(&libdatabend::sonification::Lowpass),
+                bender: None,
+                session: None,
+            },
+            DatabendEffect {
+                name: "Phaser".to_string(),
+                description: "Sweeps all-pass notches through the pixels for rippling bands"
+                    .to_string(),
+                emoji: "🌀".to_string(),
+                params: libdatabend::sonification::Phaser.params(),
+                effect: Some(&libdatabend::sonification::Phaser),
+                bender: None,
+                session: None,
+            },
+            DatabendEffect {
+                name: "Reverse".to_string(),
+                description: "Plays the pixel stream backwards, whole or in blocks".to_string(),
+                emoji: "⏪".to_string(),
+                params: libdatabend::sonification::Reverse.params(),
+                effect: Some(&libdatabend::sonification::Reverse),
+                bender: None,
+                session: None,
             },
         ];
 
@@ -107,12 +359,31 @@ impl App {
             input_path: String::new(),
             output_path: String::new(),
             params: vec![String::new(); 1],
+            seed: String::new(),
+            background: String::new(),
+            region: String::new(),
+            feather: String::new(),
+            template: String::new(),
+            seed_mode: SeedMode::default(),
+            recipe_path: String::new(),
+            recording: vec![String::new(); recording::SETTINGS.len()],
+            pipeline: Vec::new(),
+            selected_step: 0,
             current_input: InputMode::SelectingEffect,
             processing: false,
             progress: 0.0,
             status_message: "Ready — select an effect and set file paths to begin".to_string(),
             last_update: Instant::now(),
-            synesthesia_state: None,
+            session: None,
+            session_options: SaveOptions::default(),
+            job: None,
+            recorder: None,
+            key_log: None,
+            browser: None,
+            preview: None,
+            truecolor: preview::truecolor_supported(),
+            preview_dirty: false,
+            last_preview: Instant::now(),
         }
     }
 
@@ -135,83 +406,991 @@ impl App {
         self.params = vec![String::new(); param_count.max(1)];
     }
 
-    fn execute_effect(&mut self) {
+    fn add_to_pipeline(&mut self) {
+        let selected = &self.effects[self.selected_effect];
+        if selected.effect.is_none() {
+            self.status_message =
+                format!("❌ {} is interactive and can't be chained", selected.name);
+            return;
+        }
+        if self.selected_values().is_none() {
+            return;
+        }
+        let selected = &self.effects[self.selected_effect];
+        self.pipeline.push(ChainStep {
+            effect: self.selected_effect,
+            params: self.params.clone(),
+            enabled: true,
+        });
+        self.selected_step = self.pipeline.len() - 1;
+        self.status_message = format!(
+            "🔗 Added {} to the chain ({} steps)",
+            selected.name,
+            self.pipeline.len()
+        );
+    }
+
+    fn next_step(&mut self) {
+        if !self.pipeline.is_empty() {
+            self.selected_step = (self.selected_step + 1) % self.pipeline.len();
+        }
+    }
+
+    fn previous_step(&mut self) {
+        if self.selected_step > 0 {
+            self.selected_step -= 1;
+        } else {
+            self.selected_step = self.pipeline.len().saturating_sub(1);
+        }
+    }
+
+    fn move_step_up(&mut self) {
+        if self.selected_step > 0 {
+            self.pipeline
+                .swap(self.selected_step, self.selected_step - 1);
+            self.selected_step -= 1;
+        }
+    }
+
+    fn move_step_down(&mut self) {
+        if self.selected_step + 1 < self.pipeline.len() {
+            self.pipeline
+                .swap(self.selected_step, self.selected_step + 1);
+            self.selected_step += 1;
+        }
+    }
+
+    fn toggle_step(&mut self) {
+        if let Some(step) = self.pipeline.get_mut(self.selected_step) {
+            step.enabled = !step.enabled;
+        }
+    }
+
+    fn remove_step(&mut self) {
+        if self.selected_step < self.pipeline.len() {
+            self.pipeline.remove(self.selected_step);
+            self.selected_step = self
+                .selected_step
+                .min(self.pipeline.len().saturating_sub(1));
+        }
+        if self.pipeline.is_empty() {
+            self.current_input = InputMode::SelectingEffect;
+        }
+    }
+
+    fn open_browser(&mut self, target: BrowseTarget) {
+        let current = match target {
+            BrowseTarget::Input => &self.input_path,
+            BrowseTarget::Output => &self.output_path,
+            BrowseTarget::Mask => &self.region,
+            BrowseTarget::Recipe => &self.recipe_path,
+        };
+        self.browser = Some(FileBrowser::open(target, current));
+        self.current_input = InputMode::Browsing;
+    }
+
+    /// Closes the browser and returns to the path field it was opened from.
+    fn close_browser(&mut self) {
+        self.current_input = match self.browser.take().map(|browser| browser.target) {
+            Some(BrowseTarget::Output) => InputMode::OutputPath,
+            Some(BrowseTarget::Mask) => InputMode::Region,
+            Some(BrowseTarget::Recipe) => InputMode::Recipe(RecipeAction::Load),
+            _ => InputMode::InputPath,
+        };
+    }
+
+    /// Fills the path field the browser was opened for and closes it.
+    fn pick_path(&mut self, path: &Path) {
+        let path = path.to_string_lossy().into_owned();
+        match self.browser.take().map(|browser| browser.target) {
+            Some(BrowseTarget::Input) => {
+                self.input_path = path;
+                self.preview_input();
+            }
+            Some(BrowseTarget::Output) => self.output_path = path,
+            Some(BrowseTarget::Mask) => {
+                self.region = path;
+                self.current_input = InputMode::Region;
+                return;
+            }
+            Some(BrowseTarget::Recipe) => {
+                self.recipe_path = path;
+                self.current_input = InputMode::Recipe(RecipeAction::Load);
+                self.load_recipe();
+                return;
+            }
+            None => return,
+        }
+        self.current_input = InputMode::SelectingEffect;
+    }
+
+    /// Picks the browsed folder itself. As an input it makes the run a batch;
+    /// as an output it's the batch's or sequence's folder, or the folder for
+    /// an output that keeps the current file name or derives one from the input.
+    fn pick_folder(&mut self) {
+        let Some(browser) = self
+            .browser
+            .as_ref()
+            .filter(|browser| matches!(browser.target, BrowseTarget::Input | BrowseTarget::Output))
+        else {
+            return;
+        };
+        if browser.target == BrowseTarget::Input
+            || batch::is_batch_input(&self.input_path)
+            || sequence::is_sequence_input(&self.input_path)
+        {
+            let dir = browser.dir().to_path_buf();
+            self.pick_path(&dir);
+            return;
+        }
+        let name = match Path::new(&self.output_path).file_name() {
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => {
+                let stem = Path::new(&self.input_path)
+                    .file_stem()
+                    .map_or("output".into(), |stem| stem.to_string_lossy());
+                format!("{}_bent.png", stem)
+            }
+        };
+        let path = browser.dir().join(name);
+        self.pick_path(&path);
+    }
+
+    /// Shows the confirmed input file in the preview panel, or the first
+    /// image of a batch or sequence.
+    fn preview_input(&mut self) {
+        self.preview = if self.input_path.is_empty() {
+            None
+        } else if sequence::is_sequence_input(&self.input_path) {
+            match FramePattern::parse(&self.input_path).and_then(|pattern| pattern.frames()) {
+                Ok(frames) => Some(Preview::load(
+                    &format!("Sequence of {}", frames.len()),
+                    &frames[0].1.to_string_lossy(),
+                )),
+                Err(error) => {
+                    self.status_message = format!("❌ {}", error);
+                    None
+                }
+            }
+        } else if batch::is_batch_input(&self.input_path) {
+            match batch::expand(&self.input_path) {
+                Ok(inputs) => Some(Preview::load(
+                    &format!("Batch of {}", inputs.len()),
+                    &inputs[0].to_string_lossy(),
+                )),
+                Err(error) => {
+                    self.status_message = format!("❌ {}", error);
+                    None
+                }
+            }
+        } else {
+            Some(Preview::load("Input", &self.input_path))
+        };
+    }
+
+    /// Shows the freshly written output file in the preview panel.
+    fn preview_output(&mut self) {
+        self.preview = Some(Preview::load("Output", &self.output_path));
+    }
+
+    /// Called after a key changed the interactive buffer.
+    fn session_changed(&mut self, stroke: KeyStroke) {
+        self.preview_dirty = true;
+        if let Some(log) = &mut self.key_log {
+            log.push(stroke);
+        }
+        if let (Some(recorder), Some(state)) = (&mut self.recorder, &self.session) {
+            recorder.on_key(state.canvas());
+        }
+    }
+
+    /// Captures a frame when recording at fixed intervals.
+    fn record_tick(&mut self) {
+        if let (Some(recorder), Some(state)) = (&mut self.recorder, &self.session) {
+            recorder.on_tick(state.canvas());
+        }
+    }
+
+    /// Saves the interactive result and its key log, and leaves the session.
+    /// A recording is then encoded on the worker thread, next to the still
+    /// output.
+    fn finish_session(&mut self) {
+        let Some(state) = self.session.take() else {
+            return;
+        };
+        let recorder = self.recorder.take();
+        let key_log = self.key_log.take();
+        self.current_input = InputMode::SelectingEffect;
+        self.processing = false;
+
+        // The fields may have changed, or the mask gone, since the session started
+        let options = std::mem::take(&mut self.session_options);
+        match state.save(&self.output_path, &options) {
+            Ok(message) => {
+                self.status_message = message;
+                self.preview_output();
+            }
+            Err(error) => {
+                self.status_message = format!("❌ {}", error);
+                return;
+            }
+        }
+
+        if let Some(mut log) = key_log {
+            log.background = options.background;
+            let path = KeyLog::output_path(&self.output_path);
+            self.status_message = match log.save(&path) {
+                Ok(()) => {
+                    format!(
+                        "{} 🎹 {} keys logged to {}",
+                        self.status_message,
+                        log.strokes.len(),
+                        path
+                    )
+                }
+                Err(error) => format!("❌ Saved the image, but not the key log: {}", error),
+            };
+        }
+
+        if let Some(recorder) = recorder {
+            let path = recorder.output_path(&self.output_path);
+            self.start_job(None, move |progress| {
+                recorder.save(&path, progress)?;
+                Ok(JobOutput::Recording {
+                    frames: recorder.frame_count(),
+                    path,
+                })
+            });
+        }
+    }
+
+    /// Rebuilds the preview from the interactive buffer, at most once per
+    /// `LIVE_PREVIEW_INTERVAL` so fast typing doesn't stall on rescaling.
+    fn refresh_live_preview(&mut self) {
+        if !self.preview_dirty || self.last_preview.elapsed() < LIVE_PREVIEW_INTERVAL {
+            return;
+        }
+        if let Some(state) = &self.session
+            && let Ok(img) = state.canvas().image()
+        {
+            self.preview = Some(Preview::from_image("Live", &self.output_path, &img));
+        }
+        self.preview_dirty = false;
+        self.last_preview = Instant::now();
+    }
+
+    fn save_options(&self) -> Result<SaveOptions, &'static str> {
+        let background = if self.background.is_empty() {
+            None
+        } else {
+            Some(
+                imageio::parse_color(&self.background)
+                    .ok_or("background must be a RRGGBB hex colour!")?,
+            )
+        };
+        let feather = if self.feather.is_empty() {
+            0
+        } else {
+            self.feather
+                .parse()
+                .map_err(|_| "feather must be a whole number of pixels!")?
+        };
+        let region = if self.region.trim().is_empty() {
+            None
+        } else {
+            Some(
+                Region::parse(&self.region, feather)
+                    .map_err(|_| "region must be x,y,w,h rectangles or a readable mask image!")?,
+            )
+        };
+        Ok(SaveOptions {
+            background,
+            region,
+            ..SaveOptions::default()
+        })
+    }
+
+    /// Checks the paths and parses the seed and background fields before a run.
+    fn run_settings(&self) -> Result<(Option<u64>, SaveOptions), &'static str> {
         if self.input_path.is_empty() || self.output_path.is_empty() {
-            self.status_message = "❌ please specify input and output paths!".to_string();
+            return Err("please specify input and output paths!");
+        }
+
+        if !batch::is_batch_input(&self.input_path)
+            && !sequence::is_sequence_input(&self.input_path)
+            && !Path::new(&self.input_path).exists()
+        {
+            return Err("input file does not exist!");
+        }
+
+        Ok((self.parsed_seed()?, self.save_options()?))
+    }
+
+    fn parsed_seed(&self) -> Result<Option<u64>, &'static str> {
+        if self.seed.is_empty() {
+            return Ok(None);
+        }
+        self.seed
+            .parse::<u64>()
+            .map(Some)
+            .map_err(|_| "seed must be a whole number!")
+    }
+
+    /// Opens the recipe popup, suggesting `recipe.toml` next to the output.
+    fn open_recipe(&mut self, action: RecipeAction) {
+        if self.recipe_path.is_empty() {
+            self.recipe_path = Path::new(&self.output_path)
+                .with_file_name("recipe.toml")
+                .to_string_lossy()
+                .into_owned();
+        }
+        self.current_input = InputMode::Recipe(action);
+    }
+
+    fn close_recipe(&mut self, action: RecipeAction) {
+        self.current_input = if action == RecipeAction::SaveChain {
+            InputMode::Pipeline
+        } else {
+            InputMode::SelectingEffect
+        };
+    }
+
+    fn effect_index(&self, id: &str) -> Option<usize> {
+        self.effects
+            .iter()
+            .position(|effect| effect.id().eq_ignore_ascii_case(id))
+    }
+
+    /// Loads the recipe at `recipe_path`. A single effect is selected with
+    /// its parameters filled in; several replace the chain. The seed and
+    /// background fields take the recipe's, or are cleared.
+    fn load_recipe(&mut self) {
+        let loaded = Recipe::load(&self.recipe_path, |name| {
+            self.effect_index(name)
+                .map(|index| self.effects[index].params)
+        });
+        let recipe = match loaded {
+            Ok(recipe) => recipe,
+            Err(error) => {
+                self.status_message = format!("❌ {}", error);
+                return;
+            }
+        };
+
+        // Every name was found by `effect_index` while loading
+        let indices: Vec<usize> = recipe
+            .steps
+            .iter()
+            .filter_map(|step| self.effect_index(&step.effect))
+            .collect();
+        if let ([step], [index]) = (recipe.steps.as_slice(), indices.as_slice()) {
+            self.selected_effect = *index;
+            self.update_params();
+            for (field, value) in self.params.iter_mut().zip(recipe_fields(step)) {
+                *field = value;
+            }
+            self.current_input = InputMode::SelectingEffect;
+            self.status_message = format!(
+                "📜 Loaded {} from {}",
+                self.effects[*index].name, self.recipe_path
+            );
+        } else {
+            if let Some(&index) = indices
+                .iter()
+                .find(|&&index| self.effects[index].effect.is_none())
+            {
+                self.status_message = format!(
+                    "❌ {} can't be chained, so it can't share a recipe with other effects",
+                    self.effects[index].name
+                );
+                return;
+            }
+            self.pipeline = recipe
+                .steps
+                .iter()
+                .zip(&indices)
+                .map(|(step, &effect)| ChainStep {
+                    effect,
+                    params: recipe_fields(step),
+                    enabled: true,
+                })
+                .collect();
+            self.selected_step = 0;
+            self.current_input = InputMode::Pipeline;
+            self.status_message = format!(
+                "📜 Loaded a {}-step chain from {}, press Enter to run it",
+                self.pipeline.len(),
+                self.recipe_path
+            );
+        }
+        self.seed = recipe
+            .seed
+            .map_or_else(String::new, |seed| seed.to_string());
+        self.background = recipe
+            .background
+            .map_or_else(String::new, imageio::format_color);
+    }
+
+    /// Writes the selected effect, or the enabled steps of the chain, to
+    /// `recipe_path` together with the seed and background fields.
+    fn save_recipe(&mut self, action: RecipeAction) {
+        let settings = self
+            .parsed_seed()
+            .and_then(|seed| Ok((seed, self.save_options()?.background)));
+        let (seed, background) = match settings {
+            Ok(settings) => settings,
+            Err(message) => {
+                self.status_message = format!("❌ {}", message);
+                return;
+            }
+        };
+
+        let mut steps = Vec::new();
+        if action == RecipeAction::SaveChain {
+            for (i, step) in self
+                .pipeline
+                .iter()
+                .enumerate()
+                .filter(|(_, step)| step.enabled)
+            {
+                let effect = &self.effects[step.effect];
+                match ParamValues::parse(effect.params, &step.params) {
+                    Ok(values) => steps.push(RecipeStep {
+                        effect: effect.id().to_string(),
+                        specs: effect.params,
+                        values,
+                    }),
+                    Err(error) => {
+                        self.status_message = format!("❌ Step {}: {}", i + 1, error);
+                        return;
+                    }
+                }
+            }
+            if steps.is_empty() {
+                self.status_message = "❌ the chain has no enabled steps!".to_string();
+                return;
+            }
+        } else {
+            let Some(values) = self.selected_values() else {
+                return;
+            };
+            let effect = &self.effects[self.selected_effect];
+            steps.push(RecipeStep {
+                effect: effect.id().to_string(),
+                specs: effect.params,
+                values,
+            });
+        }
+
+        let recipe = Recipe {
+            seed,
+            background,
+            steps,
+        };
+        match recipe.save(&self.recipe_path) {
+            Ok(()) => {
+                self.status_message = format!("📜 Saved the recipe to {}", self.recipe_path);
+                self.close_recipe(action);
+            }
+            Err(error) => self.status_message = format!("❌ {}", error),
+        }
+    }
+
+    fn execute_pipeline(&mut self) {
+        let (seed, options) = match self.run_settings() {
+            Ok(settings) => settings,
+            Err(message) => {
+                self.status_message = format!("❌ {}", message);
+                return;
+            }
+        };
+        if batch::is_batch_input(&self.input_path) || sequence::is_sequence_input(&self.input_path)
+        {
+            self.status_message = "❌ chains run on a single input file!".to_string();
             return;
         }
 
-        if !Path::new(&self.input_path).exists() {
-            self.status_message = "❌ input file does not exist!".to_string();
+        let mut steps = Vec::new();
+        for (i, step) in self.pipeline.iter().enumerate() {
+            // A disabled step is left out, so its fields may hold anything
+            let Some(effect) = self.effects[step.effect].effect.filter(|_| step.enabled) else {
+                continue;
+            };
+            match ParamValues::parse(effect.params(), &step.params) {
+                Ok(params) => steps.push(PipelineStep {
+                    effect,
+                    params,
+                    enabled: step.enabled,
+                }),
+                Err(error) => {
+                    self.status_message = format!("❌ Step {}: {}", i + 1, error);
+                    return;
+                }
+            }
+        }
+        let pipeline = Pipeline { steps };
+        let step_count = pipeline.enabled_steps().count();
+        if step_count == 0 {
+            self.status_message = "❌ the chain has no enabled steps!".to_string();
             return;
         }
 
-        self.processing = true;
-        self.progress = 0.0;
-        self.status_message = " currently processing...".to_string();
-
-        // Execute the selected effect
-        match self.selected_effect {
-            0 => {
-                // Oversensibility
-                let iso = self.params[0].parse::<i32>().unwrap_or(800);
-                libdatabend::oversensibility::main(&self.input_path, &self.output_path, &iso);
-            }
-            1 => {
-                // Overexposure
-                let exposure = self.params[0].parse::<f32>().unwrap_or(1.5);
-                libdatabend::overexposure::main(&self.input_path, &self.output_path, exposure);
-            }
-            2 => {
-                // Synesthesia - Initialize interactive mode
-                match libdatabend::synestesia::SynesthesiaState::new(&self.input_path) {
-                    Ok(state) => {
-                        self.synesthesia_state = Some(state);
-                        self.current_input = InputMode::Processing;
-                        self.status_message = "🎹 Synesthesia mode active! Press keys to databend, ESC to finish!".to_string();
+        let (input, output) = (self.input_path.clone(), self.output_path.clone());
+        self.start_job(Some(step_count), move |progress| {
+            pipeline
+                .apply_file(&input, &output, seed, &options, progress)
+                .map(JobOutput::Seed)
+        });
+    }
+
+    /// Parses the typed-in parameters of the selected effect. On failure the
+    /// editor jumps to the offending field and the status bar says why.
+    fn selected_values(&mut self) -> Option<ParamValues> {
+        let specs = self.effects[self.selected_effect].params;
+        let parsed = ParamValues::parse(specs, &self.params);
+        self.checked_params(parsed, false)
+    }
+
+    /// Like `selected_values`, but fields may hold `start..end` ranges.
+    fn selected_ranges(&mut self) -> Option<ParamRanges> {
+        let specs = self.effects[self.selected_effect].params;
+        let parsed = ParamRanges::parse(specs, &self.params);
+        self.checked_params(parsed, true)
+    }
+
+    fn checked_params<T>(&mut self, parsed: Result<T, DatabendError>, ranges: bool) -> Option<T> {
+        let specs = self.effects[self.selected_effect].params;
+        match parsed {
+            Ok(parsed) => Some(parsed),
+            Err(error) => {
+                if let Some(index) = specs
+                    .iter()
+                    .zip(&self.params)
+                    .position(|(spec, input)| param_error(spec, input, ranges).is_some())
+                {
+                    self.current_input = InputMode::Parameters(index);
+                }
+                self.status_message = format!("❌ {}", error);
+                None
+            }
+        }
+    }
+
+    fn execute_effect(&mut self) {
+        let (seed, options) = match self.run_settings() {
+            Ok(settings) => settings,
+            Err(message) => {
+                self.status_message = format!("❌ {}", message);
+                return;
+            }
+        };
+
+        if sequence::is_sequence_input(&self.input_path) {
+            self.execute_sequence(seed, options);
+            return;
+        }
+
+        let Some(values) = self.selected_values() else {
+            return;
+        };
+
+        if batch::is_batch_input(&self.input_path) {
+            self.execute_batch(seed, options, values);
+            return;
+        }
+
+        let (input, output) = (self.input_path.clone(), self.output_path.clone());
+        let selected = &self.effects[self.selected_effect];
+        if let Some(effect) = selected.effect {
+            // Parametric effects run in memory between one load and one save
+            self.start_job(None, move |progress| {
+                imageio::apply_file(effect, &input, &output, &values, seed, &options, progress)
+                    .map(JobOutput::Seed)
+            });
+        } else if let Some(bender) = selected.bender {
+            // File benders work on the encoded bytes, not on pixels
+            self.start_job(None, move |progress| {
+                libdatabend::filebend::main(
+                    bender, &input, &output, &values, seed, &options, progress,
+                )
+                .map(JobOutput::Bent)
+            });
+        } else {
+            // Interactive effects are played key by key from the event loop
+            self.processing = true;
+            self.start_session(seed, &values, options);
+        }
+    }
+
+    /// Runs the selected effect over every image of the input folder or
+    /// pattern, into the output folder. Images that fail are skipped.
+    fn execute_batch(&mut self, seed: Option<u64>, options: SaveOptions, values: ParamValues) {
+        let selected = &self.effects[self.selected_effect];
+        let (effect, bender) = (selected.effect, selected.bender);
+        if effect.is_none() && bender.is_none() {
+            self.status_message = "❌ interactive effects can't run in a batch!".to_string();
+            return;
+        }
+        let template = if self.template.is_empty() {
+            batch::DEFAULT_TEMPLATE.to_string()
+        } else {
+            self.template.clone()
+        };
+        let inputs =
+            match batch::check_template(&template).and_then(|()| batch::expand(&self.input_path)) {
+                Ok(inputs) => inputs,
+                Err(error) => {
+                    self.status_message = format!("❌ {}", error);
+                    return;
+                }
+            };
+
+        let output_dir = PathBuf::from(&self.output_path);
+        self.start_job(None, move |progress| {
+            batch::run(
+                &inputs,
+                &output_dir,
+                &template,
+                progress,
+                |input, output, progress| {
+                    let (input, output) = (&*input.to_string_lossy(), &*output.to_string_lossy());
+                    match (effect, bender) {
+                        (Some(effect), _) => imageio::apply_file(
+                            effect, input, output, &values, seed, &options, progress,
+                        ),
+                        // A batch counts the image as written; its decoded copy is extra
+                        (None, Some(bender)) => libdatabend::filebend::main(
+                            bender, input, output, &values, seed, &options, progress,
+                        )
+                        .map(|bent| bent.seed),
+                        (None, None) => unreachable!("interactive effects were turned away"),
                     }
-                    Err(error) => {
-                        self.status_message = format!("❌ Failed to start synesthesia: {}", error);
+                },
+            )
+            .map(JobOutput::Batch)
+        });
+    }
+
+    /// Runs the selected effect over every frame of the input pattern, with
+    /// `start..end` parameters interpolated from the first frame to the last.
+    fn execute_sequence(&mut self, seed: Option<u64>, options: SaveOptions) {
+        let selected = &self.effects[self.selected_effect];
+        let (effect, bender) = (selected.effect, selected.bender);
+        if effect.is_none() && bender.is_none() {
+            self.status_message = "❌ interactive effects can't run on a sequence!".to_string();
+            return;
+        }
+        let pattern = match FramePattern::parse(&self.input_path) {
+            Ok(pattern) => pattern,
+            Err(error) => {
+                self.status_message = format!("❌ {}", error);
+                return;
+            }
+        };
+        let Some(ranges) = self.selected_ranges() else {
+            return;
+        };
+
+        let (output, seed_mode) = (self.output_path.clone(), self.seed_mode);
+        self.start_job(None, move |progress| {
+            sequence::run(
+                &pattern,
+                &output,
+                &ranges,
+                seed,
+                seed_mode,
+                progress,
+                |input, output, values, seed, progress| {
+                    let (input, output) = (&*input.to_string_lossy(), &*output.to_string_lossy());
+                    let seed = Some(seed);
+                    match (effect, bender) {
+                        (Some(effect), _) => imageio::apply_file(
+                            effect, input, output, values, seed, &options, progress,
+                        ),
+                        (None, Some(bender)) => libdatabend::filebend::main(
+                            bender, input, output, values, seed, &options, progress,
+                        )
+                        .map(|bent| bent.seed),
+                        (None, None) => unreachable!("interactive effects were turned away"),
                     }
+                },
+            )
+            .map(JobOutput::Sequence)
+        });
+    }
+
+    fn toggle_seed_mode(&mut self) {
+        self.seed_mode = match self.seed_mode {
+            SeedMode::Stable => SeedMode::Flicker,
+            SeedMode::Flicker => SeedMode::Stable,
+        };
+        self.status_message = match self.seed_mode {
+            SeedMode::Stable => "🎞️ Sequences reuse the seed on every frame (stable)",
+            SeedMode::Flicker => "🎞️ Sequences change the seed every frame (flicker)",
+        }
+        .to_string();
+    }
+
+    /// Hands `run` to a worker thread and switches to the progress overlay.
+    /// The event loop keeps drawing and picks up messages in `poll_job`.
+    fn start_job(
+        &mut self,
+        chain_steps: Option<usize>,
+        run: impl FnOnce(&Progress) -> Result<JobOutput, DatabendError> + Send + 'static,
+    ) {
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let worker_cancel = Arc::clone(&cancel);
+        thread::spawn(move || {
+            // Only whole percents reach the UI, so per-row updates stay cheap
+            let last_percent = Cell::new(u32::MAX);
+            let report = |fraction: f32| {
+                let percent = (fraction * 100.0) as u32;
+                if last_percent.replace(percent) != percent {
+                    let _ = sender.send(JobMessage::Progress(fraction));
                 }
-                return; // Don't set processing to false
-            }
-            3 => {
-                // Variations on a Cloud
-                let layers = self.params[0].parse::<u32>().unwrap_or(5);
-                let _ = libdatabend::variationsonacloud::main(
-                    &self.input_path,
-                    &self.output_path,
-                    layers,
+            };
+            let result = run(&Progress::new(&report, &worker_cancel));
+            let _ = sender.send(JobMessage::Finished(result));
+        });
+
+        self.job = Some(Job {
+            receiver,
+            chain_steps,
+            cancel,
+        });
+        self.processing = true;
+        self.progress = 0.0;
+        self.current_input = InputMode::Processing;
+        self.status_message = "⏳ Processing...".to_string();
+    }
+
+    /// Applies whatever the worker sent since the last frame, and wraps up
+    /// once it finished.
+    fn poll_job(&mut self) {
+        let Some(job) = &self.job else {
+            return;
+        };
+        let result = loop {
+            match job.receiver.try_recv() {
+                Ok(JobMessage::Progress(fraction)) => self.progress = fraction as f64 * 100.0,
+                Ok(JobMessage::Finished(result)) => {
+                    // `None` marks a run that stopped because it was cancelled
+                    break result.map_err(|error| match error {
+                        DatabendError::Cancelled => None,
+                        error => Some(error.to_string()),
+                    });
+                }
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => {
+                    break Err(Some("the effect crashed".to_string()));
+                }
+            }
+        };
+
+        let chain_steps = self.job.take().and_then(|job| job.chain_steps);
+        self.processing = false;
+        self.current_input = match (&result, chain_steps) {
+            (Err(None), _) | (_, None) => InputMode::SelectingEffect,
+            (_, Some(_)) => InputMode::Pipeline,
+        };
+        match result {
+            Ok(JobOutput::Batch(report)) => {
+                self.progress = 100.0;
+                self.status_message = batch_summary("Batch", "images", &report);
+                if let Some(output) = report.last_output() {
+                    self.preview = Some(Preview::load("Output", &output.to_string_lossy()));
+                }
+            }
+            Ok(JobOutput::Sequence(report)) => {
+                self.progress = 100.0;
+                self.status_message = format!(
+                    "{} (seed {}, {})",
+                    batch_summary("Sequence", "frames", &report.frames),
+                    report.seed,
+                    self.seed_mode.name()
                 );
+                if let Some(output) = report.frames.last_output() {
+                    self.preview = Some(Preview::load("Output", &output.to_string_lossy()));
+                }
             }
-            4 => {
-                // The Mind Electric
-                let layers = self.params[0].parse::<u32>().unwrap_or(5);
-                let _ = libdatabend::themindelectric::main(
-                    &self.input_path,
-                    &self.output_path,
-                    &layers,
+            Ok(JobOutput::Recording { path, frames }) => {
+                self.progress = 100.0;
+                self.status_message = format!(
+                    "🎞️ Saved the image and recorded {} frames to {}",
+                    frames, path
                 );
             }
-            5 => {
-                // Jack Stauberism
-                let _ = libdatabend::jackstauberism::main(&self.input_path, &self.output_path);
+            Ok(JobOutput::Seed(used_seed)) => {
+                self.progress = 100.0;
+                self.status_message = match chain_steps {
+                    Some(steps) => format!(
+                        "✅ Chain applied successfully! ({} steps, seed {})",
+                        steps, used_seed
+                    ),
+                    None => format!("✅ Effect applied successfully! (seed {})", used_seed),
+                };
+                self.preview_output();
+            }
+            Ok(JobOutput::Bent(bent)) => {
+                self.progress = 100.0;
+                self.status_message = match bent.warning() {
+                    Some(warning) => format!("⚠️ Bent with seed {}, but {}", bent.seed, warning),
+                    None => format!("✅ Effect applied successfully! (seed {})", bent.seed),
+                };
+                self.preview_output();
+            }
+            Err(None) => {
+                self.progress = 0.0;
+                self.status_message = "🛑 Cancelled — no output was written".to_string();
             }
-            6 => {
-                // New Normal
-                libdatabend::newnormal::main(&self.input_path, &self.output_path);
+            Err(Some(error)) => {
+                self.progress = 0.0;
+                self.status_message = format!("❌ {}", error);
             }
-            _ => {}
         }
+    }
 
-        self.processing = false;
-        self.progress = 100.0;
-        self.status_message = "✅ Effect applied successfully!".to_string();
+    /// Asks the worker to stop. It finishes the row it is on, drops its
+    /// result and reports back through `poll_job`.
+    fn cancel_job(&mut self) {
+        if let Some(job) = &self.job {
+            job.cancel.store(true, Ordering::Relaxed);
+            self.status_message = "🛑 Cancelling...".to_string();
+        }
+    }
+
+    fn cancelling(&self) -> bool {
+        self.job
+            .as_ref()
+            .is_some_and(|job| job.cancel.load(Ordering::Relaxed))
+    }
+
+    /// Parses the recording popup. On failure it opens on the offending
+    /// field and the status bar says why.
+    fn session_settings(&mut self) -> Option<SessionSettings> {
+        let parsed = ParamValues::parse(recording::SETTINGS, &self.recording)
+            .and_then(|values| SessionSettings::from_values(&values));
+        match parsed {
+            Ok(settings) => Some(settings),
+            Err(error) => {
+                if let Some(index) = recording::SETTINGS
+                    .iter()
+                    .zip(&self.recording)
+                    .position(|(spec, input)| param_error(spec, input, false).is_some())
+                {
+                    self.current_input = InputMode::Recording(index);
+                }
+                self.status_message = format!("❌ {}", error);
+                None
+            }
+        }
+    }
+
+    fn start_session(&mut self, seed: Option<u64>, values: &ParamValues, options: SaveOptions) {
+        let Some(settings) = self.session_settings() else {
+            self.processing = false;
+            return;
+        };
+        let selected = &self.effects[self.selected_effect];
+        let id = selected.id();
+        let Some(session) = libdatabend::start_session(id, &self.input_path, seed, values) else {
+            self.processing = false;
+            return;
+        };
+        let session = session.and_then(|mut session| {
+            if let Some(region) = &options.region {
+                session.canvas_mut().set_region(region)?;
+            }
+            Ok(session)
+        });
+
+        match session {
+            Ok(session) => {
+                self.status_message = format!(
+                    "{} {} mode active (seed {})! Press keys to databend, ESC to finish!",
+                    selected.emoji,
+                    selected.name,
+                    session.seed()
+                );
+                self.recorder = settings
+                    .record
+                    .map(|options| Recorder::new(options, session.canvas()));
+                self.key_log = settings.key_log.then(|| {
+                    let mut log = KeyLog::new(
+                        id,
+                        selected.params,
+                        values,
+                        &self.input_path,
+                        session.as_ref(),
+                    );
+                    log.region = options.region.clone();
+                    log
+                });
+                self.session = Some(session);
+                self.session_options = options;
+                self.current_input = InputMode::Processing;
+                self.preview_dirty = true;
+            }
+            Err(error) => {
+                self.processing = false;
+                self.status_message = format!("❌ Failed to start {}: {}", selected.name, error);
+            }
+        }
     }
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// The parameter fields for a recipe step, in the order of its specs.
+fn recipe_fields(step: &RecipeStep) -> Vec<String> {
+    step.specs
+        .iter()
+        .map(|spec| {
+            step.values
+                .get(spec.name)
+                .map_or_else(String::new, |value| spec.format_value(value))
+        })
+        .collect()
+}
+
+/// Why a typed-in parameter is unusable, in a few words for the editor.
+/// Empty input is fine: it stands for the default shown next to it. With
+/// `ranges`, `start..end` is accepted too.
+fn param_error(spec: &ParamSpec, input: &str, ranges: bool) -> Option<String> {
+    if input.trim().is_empty() {
+        return None;
+    }
+    let parsed = if ranges {
+        spec.parse_range(input).map(|_| ())
+    } else {
+        spec.parse(input).map(|_| ())
+    };
+    match parsed {
+        Ok(_) => None,
+        Err(DatabendError::InvalidParameter { reason, .. }) => Some(reason),
+        Err(error) => Some(error.to_string()),
+    }
+}
+
+/// Keeps the panic hook from printing panics that `filebend::decode()` catches,
+/// which would otherwise write over the TUI or clutter the CLI's stderr.
+fn silence_decoder_panics() {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if !filebend::decoding() {
+            previous(info);
+        }
+    }));
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    silence_decoder_panics();
+
+    // Any arguments mean headless mode; the TUI only starts without them
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        std::process::exit(cli::run(&args));
+    }
+
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -255,16 +1434,78 @@ fn run_app(terminal: &mut Tui, app: &mut App) -> io::Result<()> {
                             KeyCode::Down | KeyCode::Char('j') => app.next_effect(),
                             KeyCode::Char('i') => app.current_input = InputMode::InputPath,
                             KeyCode::Char('o') => app.current_input = InputMode::OutputPath,
-                            KeyCode::Char('p') => {
-                                if !app.effects[app.selected_effect].params.is_empty() {
-                                    app.current_input = InputMode::Parameters(0);
-                                }
+                            KeyCode::Char('s') => app.current_input = InputMode::Seed,
+                            KeyCode::Char('b') => app.current_input = InputMode::Background,
+                            KeyCode::Char('g') => app.current_input = InputMode::Region,
+                            KeyCode::Char('h') => app.current_input = InputMode::Feather,
+                            KeyCode::Char('t') => app.current_input = InputMode::Template,
+                            KeyCode::Char('v') => app.current_input = InputMode::Recording(0),
+                            KeyCode::Char('f') => app.toggle_seed_mode(),
+                            KeyCode::Char('r') => app.open_recipe(RecipeAction::Load),
+                            KeyCode::Char('e') => app.open_recipe(RecipeAction::SaveEffect),
+                            KeyCode::Char('p')
+                                if !app.effects[app.selected_effect].params.is_empty() =>
+                            {
+                                app.current_input = InputMode::Parameters(0);
+                            }
+                            KeyCode::Char('a') => app.add_to_pipeline(),
+                            KeyCode::Char('c') if !app.pipeline.is_empty() => {
+                                app.current_input = InputMode::Pipeline;
                             }
                             KeyCode::Enter => app.execute_effect(),
                             _ => {}
                         },
+                        InputMode::Pipeline => match key.code {
+                            KeyCode::Esc => app.current_input = InputMode::SelectingEffect,
+                            KeyCode::Up | KeyCode::Char('k') => app.previous_step(),
+                            KeyCode::Down | KeyCode::Char('j') => app.next_step(),
+                            KeyCode::Char('K') => app.move_step_up(),
+                            KeyCode::Char('J') => app.move_step_down(),
+                            KeyCode::Char(' ') => app.toggle_step(),
+                            KeyCode::Char('d') | KeyCode::Delete => app.remove_step(),
+                            KeyCode::Char('e') => app.open_recipe(RecipeAction::SaveChain),
+                            KeyCode::Enter => app.execute_pipeline(),
+                            _ => {}
+                        },
+                        InputMode::Recipe(action) => match key.code {
+                            KeyCode::Tab if action == RecipeAction::Load => {
+                                app.open_browser(BrowseTarget::Recipe);
+                            }
+                            KeyCode::Enter if action == RecipeAction::Load => app.load_recipe(),
+                            KeyCode::Enter => app.save_recipe(action),
+                            KeyCode::Esc => app.close_recipe(action),
+                            KeyCode::Backspace => {
+                                app.recipe_path.pop();
+                            }
+                            KeyCode::Char(c) => app.recipe_path.push(c),
+                            _ => {}
+                        },
+                        InputMode::Browsing => {
+                            let Some(browser) = &mut app.browser else {
+                                continue;
+                            };
+                            match key.code {
+                                KeyCode::Esc => app.close_browser(),
+                                KeyCode::Up | KeyCode::Char('k') => browser.previous(),
+                                KeyCode::Down | KeyCode::Char('j') => browser.next(),
+                                KeyCode::Left | KeyCode::Backspace | KeyCode::Char('h') => {
+                                    browser.parent();
+                                }
+                                KeyCode::Right | KeyCode::Enter | KeyCode::Char('l') => {
+                                    if let Some(path) = browser.activate() {
+                                        app.pick_path(&path);
+                                    }
+                                }
+                                KeyCode::Char('.') => app.pick_folder(),
+                                _ => {}
+                            }
+                        }
                         InputMode::InputPath => match key.code {
-                            KeyCode::Enter => app.current_input = InputMode::SelectingEffect,
+                            KeyCode::Tab => app.open_browser(BrowseTarget::Input),
+                            KeyCode::Enter => {
+                                app.current_input = InputMode::SelectingEffect;
+                                app.preview_input();
+                            }
                             KeyCode::Esc => app.current_input = InputMode::SelectingEffect,
                             KeyCode::Backspace => {
                                 app.input_path.pop();
@@ -273,6 +1514,7 @@ fn run_app(terminal: &mut Tui, app: &mut App) -> io::Result<()> {
                             _ => {}
                         },
                         InputMode::OutputPath => match key.code {
+                            KeyCode::Tab => app.open_browser(BrowseTarget::Output),
                             KeyCode::Enter => app.current_input = InputMode::SelectingEffect,
                             KeyCode::Esc => app.current_input = InputMode::SelectingEffect,
                             KeyCode::Backspace => {
@@ -281,50 +1523,135 @@ fn run_app(terminal: &mut Tui, app: &mut App) -> io::Result<()> {
                             KeyCode::Char(c) => app.output_path.push(c),
                             _ => {}
                         },
+                        InputMode::Seed => match key.code {
+                            KeyCode::Enter => app.current_input = InputMode::SelectingEffect,
+                            KeyCode::Esc => app.current_input = InputMode::SelectingEffect,
+                            KeyCode::Backspace => {
+                                app.seed.pop();
+                            }
+                            KeyCode::Char(c) if c.is_ascii_digit() => app.seed.push(c),
+                            _ => {}
+                        },
+                        InputMode::Background => match key.code {
+                            KeyCode::Enter => app.current_input = InputMode::SelectingEffect,
+                            KeyCode::Esc => app.current_input = InputMode::SelectingEffect,
+                            KeyCode::Backspace => {
+                                app.background.pop();
+                            }
+                            KeyCode::Char(c) if c.is_ascii_hexdigit() || c == '#' => {
+                                app.background.push(c)
+                            }
+                            _ => {}
+                        },
+                        InputMode::Region => match key.code {
+                            KeyCode::Tab => app.open_browser(BrowseTarget::Mask),
+                            KeyCode::Enter => app.current_input = InputMode::SelectingEffect,
+                            KeyCode::Esc => app.current_input = InputMode::SelectingEffect,
+                            KeyCode::Backspace => {
+                                app.region.pop();
+                            }
+                            KeyCode::Char(c) => app.region.push(c),
+                            _ => {}
+                        },
+                        InputMode::Feather => match key.code {
+                            KeyCode::Enter => app.current_input = InputMode::SelectingEffect,
+                            KeyCode::Esc => app.current_input = InputMode::SelectingEffect,
+                            KeyCode::Backspace => {
+                                app.feather.pop();
+                            }
+                            KeyCode::Char(c) if c.is_ascii_digit() => app.feather.push(c),
+                            _ => {}
+                        },
+                        InputMode::Template => match key.code {
+                            KeyCode::Enter => app.current_input = InputMode::SelectingEffect,
+                            KeyCode::Esc => app.current_input = InputMode::SelectingEffect,
+                            KeyCode::Backspace => {
+                                app.template.pop();
+                            }
+                            KeyCode::Char(c) => app.template.push(c),
+                            _ => {}
+                        },
                         InputMode::Parameters(idx) => match key.code {
-                            KeyCode::Enter => {
+                            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                                 app.execute_effect();
-                                app.current_input = InputMode::Processing;
-                                app.processing = true;
-                                app.progress = 0.0;
                             }
-
+                            KeyCode::Tab => {
+                                app.current_input =
+                                    InputMode::Parameters((idx + 1) % app.params.len());
+                            }
+                            KeyCode::BackTab => {
+                                let count = app.params.len();
+                                app.current_input =
+                                    InputMode::Parameters((idx + count - 1) % count);
+                            }
+                            KeyCode::Enter => app.current_input = InputMode::SelectingEffect,
                             KeyCode::Esc => app.current_input = InputMode::SelectingEffect,
+                            KeyCode::Up | KeyCode::Down => {
+                                let spec = &app.effects[app.selected_effect].params[idx];
+                                let delta = if key.code == KeyCode::Up { 1 } else { -1 };
+                                app.params[idx] = spec.nudge(&app.params[idx], delta);
+                            }
                             KeyCode::Backspace => {
                                 app.params[idx].pop();
                             }
                             KeyCode::Char(c) => app.params[idx].push(c),
                             _ => {}
                         },
+                        InputMode::Recording(idx) => match key.code {
+                            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                app.execute_effect();
+                            }
+                            KeyCode::Tab => {
+                                let count = app.recording.len();
+                                app.current_input = InputMode::Recording((idx + 1) % count);
+                            }
+                            KeyCode::BackTab => {
+                                let count = app.recording.len();
+                                app.current_input = InputMode::Recording((idx + count - 1) % count);
+                            }
+                            KeyCode::Enter => app.current_input = InputMode::SelectingEffect,
+                            KeyCode::Esc => app.current_input = InputMode::SelectingEffect,
+                            KeyCode::Up | KeyCode::Down => {
+                                let delta = if key.code == KeyCode::Up { 1 } else { -1 };
+                                let spec = &recording::SETTINGS[idx];
+                                app.recording[idx] = spec.nudge(&app.recording[idx], delta);
+                            }
+                            KeyCode::Backspace => {
+                                app.recording[idx].pop();
+                            }
+                            KeyCode::Char(c) => app.recording[idx].push(c),
+                            _ => {}
+                        },
                         InputMode::Processing => {
-                            if app.synesthesia_state.is_some() {
-                                // Special handling for synesthesia mode
+                            if app.session.is_some() {
+                                // Interactive effects take every key
+                                let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
                                 match key.code {
-                                    KeyCode::Esc => {
-                                        // Save and exit synesthesia mode
-                                        if let Some(state) = &app.synesthesia_state {
-                                            match state.save(&app.output_path) {
-                                                Ok(message) => app.status_message = message,
-                                                Err(error) => app.status_message = format!("❌ {}", error),
-                                            }
+                                    KeyCode::Char('z') if ctrl => {
+                                        if let Some(state) = &mut app.session {
+                                            app.status_message = state.undo();
+                                            app.session_changed(KeyStroke::Undo);
+                                        }
+                                    }
+                                    KeyCode::Char('y') if ctrl => {
+                                        if let Some(state) = &mut app.session {
+                                            app.status_message = state.redo();
+                                            app.session_changed(KeyStroke::Redo);
                                         }
-                                        app.synesthesia_state = None;
-                                        app.current_input = InputMode::SelectingEffect;
-                                        app.processing = false;
                                     }
+                                    // Save and leave the interactive session
+                                    KeyCode::Esc => app.finish_session(),
                                     KeyCode::Char(c) => {
-                                        // Process the key press in synesthesia mode
-                                        if let Some(state) = &mut app.synesthesia_state {
+                                        // Bend the buffer with this key
+                                        if let Some(state) = &mut app.session {
                                             app.status_message = state.process_key(c);
+                                            app.session_changed(KeyStroke::Key(c));
                                         }
                                     }
                                     _ => {}
                                 }
-                            } else {
-                                // Normal processing mode
-                                if key.code == KeyCode::Esc {
-                                    app.current_input = InputMode::SelectingEffect;
-                                }
+                            } else if key.code == KeyCode::Esc {
+                                app.cancel_job();
                             }
                         }
                     }
@@ -332,10 +1659,9 @@ fn run_app(terminal: &mut Tui, app: &mut App) -> io::Result<()> {
             }
         }
 
-        // Update progress animation
-        if app.processing {
-            app.progress = (app.progress + 2.0).min(100.0);
-        }
+        app.poll_job();
+        app.record_tick();
+        app.refresh_live_preview();
     }
 }
 
@@ -344,7 +1670,17 @@ fn mode_label(mode: &InputMode) -> &'static str {
         InputMode::SelectingEffect => "Select Effect",
         InputMode::InputPath => "Editing Input Path",
         InputMode::OutputPath => "Editing Output Path",
+        InputMode::Seed => "Editing Seed",
+        InputMode::Background => "Editing Background",
+        InputMode::Region => "Editing Region",
+        InputMode::Feather => "Editing Feather",
+        InputMode::Template => "Editing Batch Names",
         InputMode::Parameters(_) => "Editing Parameters",
+        InputMode::Recording(_) => "Editing Recording",
+        InputMode::Pipeline => "Editing Chain",
+        InputMode::Browsing => "Browsing Files",
+        InputMode::Recipe(RecipeAction::Load) => "Loading Recipe",
+        InputMode::Recipe(_) => "Saving Recipe",
         InputMode::Processing => "Processing",
     }
 }
@@ -381,6 +1717,11 @@ fn ui(f: &mut Frame, app: &App) {
         .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
         .split(chunks[1]);
 
+    let left_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .split(main_chunks[0]);
+
     // Effects list
     let effects: Vec<ListItem> = app
         .effects
@@ -414,21 +1755,84 @@ fn ui(f: &mut Frame, app: &App) {
         )
         .highlight_style(Style::default().add_modifier(Modifier::BOLD));
 
-    f.render_widget(effects_list, main_chunks[0]);
+    f.render_widget(effects_list, left_chunks[0]);
+
+    // Effect chain
+    let chain_active = app.current_input == InputMode::Pipeline;
+    let chain_items: Vec<ListItem> = app
+        .pipeline
+        .iter()
+        .enumerate()
+        .map(|(i, step)| {
+            let effect = &app.effects[step.effect];
+            let params = step
+                .params
+                .iter()
+                .filter(|value| !value.is_empty())
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", ");
+            let mut style = if step.enabled {
+                Style::default()
+            } else {
+                Style::default()
+                    .fg(Color::DarkGray)
+                    .add_modifier(Modifier::CROSSED_OUT)
+            };
+            if chain_active && i == app.selected_step {
+                style = style.bg(Color::Blue).fg(Color::White);
+            }
+            ListItem::new(Line::from(vec![
+                Span::raw(format!("{}. {} ", i + 1, &effect.emoji)),
+                Span::styled(&*effect.name, style),
+                Span::styled(
+                    if params.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" ({})", params)
+                    },
+                    Style::default().fg(Color::Cyan),
+                ),
+            ]))
+        })
+        .collect();
+    let chain_border = if chain_active {
+        Style::default().fg(Color::Green)
+    } else {
+        Style::default().fg(Color::DarkGray)
+    };
+    let chain = if chain_items.is_empty() {
+        List::new(vec![ListItem::new(Span::styled(
+            "Press [a] to add the selected effect",
+            Style::default().fg(Color::DarkGray),
+        ))])
+    } else {
+        List::new(chain_items)
+    }
+    .block(
+        Block::default()
+            .title("Chain [c]")
+            .borders(Borders::ALL)
+            .border_style(chain_border),
+    );
+    f.render_widget(chain, left_chunks[1]);
 
     // Right panel
+    let selected = &app.effects[app.selected_effect];
     let right_chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Length(4), // Description
-            Constraint::Length(3), // Input path
-            Constraint::Length(3), // Output path
-            Constraint::Min(3),    // Parameters
+            Constraint::Length(4),                                       // Description
+            Constraint::Length(3),                                       // Input path
+            Constraint::Length(3),                                       // Output path
+            Constraint::Length(3), // Seed, background, batch names
+            Constraint::Length(3), // Region, feather
+            Constraint::Length(selected.params.len().max(1) as u16 + 2), // Parameters
+            Constraint::Min(6),    // Preview
         ])
         .split(main_chunks[1]);
 
     // Description
-    let selected = &app.effects[app.selected_effect];
     let desc = Paragraph::new(format!("{} {}", selected.emoji, selected.description))
         .style(Style::default().fg(Color::Yellow))
         .block(
@@ -454,13 +1858,12 @@ fn ui(f: &mut Frame, app: &App) {
     } else {
         Span::styled(&*app.input_path, input_style)
     };
-    let input = Paragraph::new(Line::from(input_content))
-        .block(
-            Block::default()
-                .title("Input Path [i]")
-                .borders(Borders::ALL)
-                .border_style(input_style),
-        );
+    let input = Paragraph::new(Line::from(input_content)).block(
+        Block::default()
+            .title("Input Path [i]")
+            .borders(Borders::ALL)
+            .border_style(input_style),
+    );
     f.render_widget(input, right_chunks[1]);
 
     // Output path
@@ -477,15 +1880,151 @@ fn ui(f: &mut Frame, app: &App) {
     } else {
         Span::styled(&*app.output_path, output_style)
     };
-    let output = Paragraph::new(Line::from(output_content))
-        .block(
-            Block::default()
-                .title("Output Path [o]")
-                .borders(Borders::ALL)
-                .border_style(output_style),
-        );
+    let output = Paragraph::new(Line::from(output_content)).block(
+        Block::default()
+            .title(if batch::is_batch_input(&app.input_path) {
+                "Output Folder [o]"
+            } else if sequence::is_sequence_input(&app.input_path) {
+                "Output Pattern or Folder [o]"
+            } else {
+                "Output Path [o]"
+            })
+            .borders(Borders::ALL)
+            .border_style(output_style),
+    );
     f.render_widget(output, right_chunks[2]);
 
+    let settings_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(30),
+            Constraint::Percentage(30),
+            Constraint::Percentage(40),
+        ])
+        .split(right_chunks[3]);
+
+    // Seed
+    let seed_active = app.current_input == InputMode::Seed;
+    let seed_style = if seed_active {
+        Style::default().fg(Color::Green)
+    } else {
+        Style::default()
+    };
+    let seed_content = if app.seed.is_empty() && !seed_active {
+        Span::styled("(random)", Style::default().fg(Color::DarkGray))
+    } else if seed_active {
+        Span::styled(format!("{}▏", &app.seed), seed_style)
+    } else {
+        Span::styled(&*app.seed, seed_style)
+    };
+    let seed = Paragraph::new(Line::from(seed_content)).block(
+        Block::default()
+            .title(if sequence::is_sequence_input(&app.input_path) {
+                format!("Seed [s] · {} [f]", app.seed_mode.name())
+            } else {
+                "Seed [s]".to_string()
+            })
+            .borders(Borders::ALL)
+            .border_style(seed_style),
+    );
+    f.render_widget(seed, settings_chunks[0]);
+
+    // Background
+    let background_active = app.current_input == InputMode::Background;
+    let background_style = if background_active {
+        Style::default().fg(Color::Green)
+    } else {
+        Style::default()
+    };
+    let background_content = if app.background.is_empty() && !background_active {
+        Span::styled("(keep alpha)", Style::default().fg(Color::DarkGray))
+    } else if background_active {
+        Span::styled(format!("{}▏", &app.background), background_style)
+    } else {
+        Span::styled(&*app.background, background_style)
+    };
+    let background = Paragraph::new(Line::from(background_content)).block(
+        Block::default()
+            .title("Background [b]")
+            .borders(Borders::ALL)
+            .border_style(background_style),
+    );
+    f.render_widget(background, settings_chunks[1]);
+
+    // Batch names
+    let template_active = app.current_input == InputMode::Template;
+    let template_style = if template_active {
+        Style::default().fg(Color::Green)
+    } else {
+        Style::default()
+    };
+    let template_content = if app.template.is_empty() && !template_active {
+        Span::styled(
+            batch::DEFAULT_TEMPLATE,
+            Style::default().fg(Color::DarkGray),
+        )
+    } else if template_active {
+        Span::styled(format!("{}▏", &app.template), template_style)
+    } else {
+        Span::styled(&*app.template, template_style)
+    };
+    let template = Paragraph::new(Line::from(template_content)).block(
+        Block::default()
+            .title("Batch Names [t]")
+            .borders(Borders::ALL)
+            .border_style(template_style),
+    );
+    f.render_widget(template, settings_chunks[2]);
+
+    let region_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(70), Constraint::Percentage(30)])
+        .split(right_chunks[4]);
+
+    // Region
+    let region_active = app.current_input == InputMode::Region;
+    let region_style = if region_active {
+        Style::default().fg(Color::Green)
+    } else {
+        Style::default()
+    };
+    let region_content = if app.region.is_empty() && !region_active {
+        Span::styled("(whole image)", Style::default().fg(Color::DarkGray))
+    } else if region_active {
+        Span::styled(format!("{}▏", &app.region), region_style)
+    } else {
+        Span::styled(&*app.region, region_style)
+    };
+    let region = Paragraph::new(Line::from(region_content)).block(
+        Block::default()
+            .title("Region [g]")
+            .borders(Borders::ALL)
+            .border_style(region_style),
+    );
+    f.render_widget(region, region_chunks[0]);
+
+    // Feather
+    let feather_active = app.current_input == InputMode::Feather;
+    let feather_style = if feather_active {
+        Style::default().fg(Color::Green)
+    } else {
+        Style::default()
+    };
+    let feather_content = if app.feather.is_empty() && !feather_active {
+        Span::styled("(hard edge)", Style::default().fg(Color::DarkGray))
+    } else if feather_active {
+        Span::styled(format!("{}▏ px", &app.feather), feather_style)
+    } else {
+        Span::styled(format!("{} px", &app.feather), feather_style)
+    };
+    let feather = Paragraph::new(Line::from(feather_content)).block(
+        Block::default()
+            .title("Feather [h]")
+            .borders(Borders::ALL)
+            .border_style(feather_style),
+    );
+    f.render_widget(feather, region_chunks[1]);
+
     // Parameters
     let param_border = if matches!(app.current_input, InputMode::Parameters(_)) {
         Style::default().fg(Color::Green)
@@ -494,34 +2033,12 @@ fn ui(f: &mut Frame, app: &App) {
     };
 
     if !selected.params.is_empty() {
-        let param_text = selected
-            .params
-            .iter()
-            .zip(&app.params)
-            .enumerate()
-            .map(|(i, (param_name, value))| {
-                let editing = matches!(app.current_input, InputMode::Parameters(idx) if idx == i);
-                let style = if editing {
-                    Style::default().fg(Color::Green)
-                } else {
-                    Style::default()
-                };
-                let display = if editing {
-                    format!("{}▏", value)
-                } else if value.is_empty() {
-                    "(default)".to_string()
-                } else {
-                    value.clone()
-                };
-                Line::from(vec![
-                    Span::styled(
-                        format!("{}: ", param_name),
-                        Style::default().fg(Color::Cyan),
-                    ),
-                    Span::styled(display, style),
-                ])
-            })
-            .collect::<Vec<_>>();
+        let ranges = sequence::is_sequence_input(&app.input_path);
+        let editing = match app.current_input {
+            InputMode::Parameters(idx) => Some(idx),
+            _ => None,
+        };
+        let param_text = param_lines(selected.params, &app.params, editing, ranges);
 
         let params = Paragraph::new(Text::from(param_text)).block(
             Block::default()
@@ -529,17 +2046,35 @@ fn ui(f: &mut Frame, app: &App) {
                 .borders(Borders::ALL)
                 .border_style(param_border),
         );
-        f.render_widget(params, right_chunks[3]);
+        f.render_widget(params, right_chunks[5]);
     } else {
-        let no_params = Paragraph::new(
-            Span::styled("This effect has no configurable parameters.", Style::default().fg(Color::DarkGray))
-        ).block(
+        let no_params = Paragraph::new(Span::styled(
+            "This effect has no configurable parameters.",
+            Style::default().fg(Color::DarkGray),
+        ))
+        .block(
             Block::default()
                 .title("Parameters")
                 .borders(Borders::ALL)
                 .border_style(Style::default().fg(Color::DarkGray)),
         );
-        f.render_widget(no_params, right_chunks[3]);
+        f.render_widget(no_params, right_chunks[5]);
+    }
+
+    // Preview
+    let preview_block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Magenta));
+    match &app.preview {
+        Some(preview) => preview.render(f, right_chunks[6], preview_block, app.truecolor),
+        None => {
+            let hint = Paragraph::new(Span::styled(
+                "Confirm an input path [i] to preview it here.",
+                Style::default().fg(Color::DarkGray),
+            ))
+            .block(preview_block.title("Preview"));
+            f.render_widget(hint, right_chunks[6]);
+        }
     }
 
     // Status bar — context-sensitive help per mode
@@ -551,38 +2086,189 @@ fn ui(f: &mut Frame, app: &App) {
             Span::raw(" input path  "),
             Span::styled("o", Style::default().fg(Color::Cyan)),
             Span::raw(" output path  "),
+            Span::styled("s", Style::default().fg(Color::Cyan)),
+            Span::raw(" seed  "),
+            Span::styled("b", Style::default().fg(Color::Cyan)),
+            Span::raw(" background  "),
+            Span::styled("g/h", Style::default().fg(Color::Cyan)),
+            Span::raw(" region/feather  "),
+            Span::styled("t", Style::default().fg(Color::Cyan)),
+            Span::raw(" names  "),
+            Span::styled("v", Style::default().fg(Color::Cyan)),
+            Span::raw(" recording  "),
+            Span::styled("f", Style::default().fg(Color::Cyan)),
+            Span::raw(" seed mode  "),
+            Span::styled("r/e", Style::default().fg(Color::Cyan)),
+            Span::raw(" load/save recipe  "),
             Span::styled("p", Style::default().fg(Color::Cyan)),
             Span::raw(" params  "),
+            Span::styled("a", Style::default().fg(Color::Cyan)),
+            Span::raw(" add to chain  "),
+            Span::styled("c", Style::default().fg(Color::Cyan)),
+            Span::raw(" chain  "),
             Span::styled("Enter", Style::default().fg(Color::Cyan)),
             Span::raw(" run  "),
             Span::styled("q/Esc", Style::default().fg(Color::Red)),
             Span::raw(" quit"),
         ]),
         InputMode::InputPath | InputMode::OutputPath => Line::from(vec![
-            Span::raw("Type a file path, then press "),
+            Span::raw("Type a file path or press "),
+            Span::styled("Tab", Style::default().fg(Color::Cyan)),
+            Span::raw(" to browse, then "),
+            Span::styled("Enter", Style::default().fg(Color::Cyan)),
+            Span::raw(" to confirm or "),
+            Span::styled("Esc", Style::default().fg(Color::Red)),
+            Span::raw(" to cancel"),
+        ]),
+        InputMode::Seed => Line::from(vec![
+            Span::raw("Type a whole number (leave empty for random), then press "),
+            Span::styled("Enter", Style::default().fg(Color::Cyan)),
+            Span::raw(" to confirm or "),
+            Span::styled("Esc", Style::default().fg(Color::Red)),
+            Span::raw(" to cancel"),
+        ]),
+        InputMode::Background => Line::from(vec![
+            Span::raw("Type a RRGGBB colour to flatten transparency onto (empty keeps alpha), "),
+            Span::raw("then press "),
+            Span::styled("Enter", Style::default().fg(Color::Cyan)),
+            Span::raw(" to confirm or "),
+            Span::styled("Esc", Style::default().fg(Color::Red)),
+            Span::raw(" to cancel"),
+        ]),
+        InputMode::Region => Line::from(vec![
+            Span::raw("Type x,y,w,h rectangles in px or % (split by ;) or a mask path, "),
+            Span::styled("Tab", Style::default().fg(Color::Cyan)),
+            Span::raw(" to browse for a mask, "),
+            Span::styled("Enter", Style::default().fg(Color::Cyan)),
+            Span::raw(" to confirm or "),
+            Span::styled("Esc", Style::default().fg(Color::Red)),
+            Span::raw(" to cancel"),
+        ]),
+        InputMode::Feather => Line::from(vec![
+            Span::raw("Type how many pixels the region's edge fades over (empty = hard edge), "),
+            Span::raw("then press "),
             Span::styled("Enter", Style::default().fg(Color::Cyan)),
             Span::raw(" to confirm or "),
             Span::styled("Esc", Style::default().fg(Color::Red)),
             Span::raw(" to cancel"),
         ]),
-        InputMode::Parameters(_) => Line::from(vec![
-            Span::raw("Type a value, then press "),
+        InputMode::Template => Line::from(vec![
+            Span::raw("Name batch outputs with {stem}, {ext} and {name} "),
+            Span::raw("(empty = {stem}_bent.{ext}), then press "),
             Span::styled("Enter", Style::default().fg(Color::Cyan)),
-            Span::raw(" to run or "),
+            Span::raw(" to confirm or "),
             Span::styled("Esc", Style::default().fg(Color::Red)),
             Span::raw(" to cancel"),
         ]),
+        InputMode::Parameters(_) | InputMode::Recording(_) => Line::from(vec![
+            Span::raw("Type a value  "),
+            Span::styled("↑/↓", Style::default().fg(Color::Cyan)),
+            Span::raw(" step  "),
+            Span::styled("Tab/Shift+Tab", Style::default().fg(Color::Cyan)),
+            Span::raw(" next/previous field  "),
+            Span::styled("Ctrl+R", Style::default().fg(Color::Cyan)),
+            Span::raw(" run  "),
+            Span::styled("Enter/Esc", Style::default().fg(Color::Red)),
+            Span::raw(" done"),
+        ]),
+        InputMode::Pipeline => Line::from(vec![
+            Span::styled("↑↓/j/k", Style::default().fg(Color::Cyan)),
+            Span::raw(" select  "),
+            Span::styled("J/K", Style::default().fg(Color::Cyan)),
+            Span::raw(" move  "),
+            Span::styled("Space", Style::default().fg(Color::Cyan)),
+            Span::raw(" enable/disable  "),
+            Span::styled("d", Style::default().fg(Color::Cyan)),
+            Span::raw(" remove  "),
+            Span::styled("e", Style::default().fg(Color::Cyan)),
+            Span::raw(" save as recipe  "),
+            Span::styled("Enter", Style::default().fg(Color::Cyan)),
+            Span::raw(" run chain  "),
+            Span::styled("Esc", Style::default().fg(Color::Red)),
+            Span::raw(" back"),
+        ]),
+        InputMode::Recipe(action) => {
+            let mut spans = vec![Span::raw("Type the recipe file path")];
+            if *action == RecipeAction::Load {
+                spans.push(Span::raw(" or press "));
+                spans.push(Span::styled("Tab", Style::default().fg(Color::Cyan)));
+                spans.push(Span::raw(" to browse"));
+            }
+            spans.push(Span::raw(", then press "));
+            spans.push(Span::styled("Enter", Style::default().fg(Color::Cyan)));
+            spans.push(Span::raw(if *action == RecipeAction::Load {
+                " to load it or "
+            } else {
+                " to save it or "
+            }));
+            spans.push(Span::styled("Esc", Style::default().fg(Color::Red)));
+            spans.push(Span::raw(" to cancel"));
+            Line::from(spans)
+        }
+        InputMode::Browsing => {
+            let mut spans = vec![
+                Span::styled("↑↓/j/k", Style::default().fg(Color::Cyan)),
+                Span::raw(" select  "),
+                Span::styled("Enter", Style::default().fg(Color::Cyan)),
+                Span::raw(" open/pick  "),
+                Span::styled("Backspace/←", Style::default().fg(Color::Cyan)),
+                Span::raw(" parent  "),
+            ];
+            spans.push(Span::styled(".", Style::default().fg(Color::Cyan)));
+            spans.push(Span::raw(
+                if app
+                    .browser
+                    .as_ref()
+                    .is_some_and(|browser| browser.target == BrowseTarget::Input)
+                {
+                    " batch this folder  "
+                } else {
+                    " use this folder  "
+                },
+            ));
+            spans.push(Span::styled("Esc", Style::default().fg(Color::Red)));
+            spans.push(Span::raw(" back"));
+            Line::from(spans)
+        }
         InputMode::Processing => {
-            if app.synesthesia_state.is_some() {
+            if let Some(state) = &app.session {
                 Line::from(vec![
                     Span::raw("Press keys to databend the image. "),
+                    Span::styled("Ctrl+Z", Style::default().fg(Color::Cyan)),
+                    Span::raw(" undo  "),
+                    Span::styled("Ctrl+Y", Style::default().fg(Color::Cyan)),
+                    Span::raw(" redo  "),
                     Span::styled("Esc", Style::default().fg(Color::Red)),
-                    Span::raw(" to save and exit"),
+                    Span::raw(" to save and exit  "),
+                    Span::styled(
+                        format!(
+                            "History: {} undo / {} redo  ",
+                            state.canvas().history.undo_depth(),
+                            state.canvas().history.redo_depth()
+                        ),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    match &app.recorder {
+                        Some(recorder) => Span::styled(
+                            format!(
+                                "● REC {} {}/{}",
+                                match recorder.options().format {
+                                    AnimationFormat::Gif => "GIF",
+                                    AnimationFormat::Apng => "APNG",
+                                },
+                                recorder.frame_count(),
+                                recorder.options().max_frames
+                            ),
+                            Style::default().fg(Color::Red),
+                        ),
+                        None => Span::raw(""),
+                    },
                 ])
             } else {
                 Line::from(vec![
+                    Span::raw("Working... the preview updates when the run finishes  "),
                     Span::styled("Esc", Style::default().fg(Color::Red)),
-                    Span::raw(" to return"),
+                    Span::raw(" cancel"),
                 ])
             }
         }
@@ -593,7 +2279,9 @@ fn ui(f: &mut Frame, app: &App) {
             Span::styled("Mode: ", Style::default().fg(Color::White)),
             Span::styled(
                 mode_label(&app.current_input),
-                Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
+                Style::default()
+                    .fg(Color::Magenta)
+                    .add_modifier(Modifier::BOLD),
             ),
             Span::raw("  "),
             Span::styled(&*app.status_message, Style::default().fg(Color::Green)),
@@ -609,14 +2297,71 @@ fn ui(f: &mut Frame, app: &App) {
     );
     f.render_widget(status, chunks[2]);
 
-    // Processing overlay
-    if app.processing {
+    if let InputMode::Recipe(action) = app.current_input {
+        let area = centered_rect(60, 20, f.area());
+        f.render_widget(Clear, area);
+        let title = match action {
+            RecipeAction::Load => "Load Recipe",
+            RecipeAction::SaveEffect => "Save Effect as Recipe",
+            RecipeAction::SaveChain => "Save Chain as Recipe",
+        };
+        let popup = Paragraph::new(Text::from(vec![
+            Line::from(Span::styled(
+                format!("{}▏", &app.recipe_path),
+                Style::default().fg(Color::Green),
+            )),
+            Line::from(""),
+            Line::from(Span::styled(
+                "A seed, a background and one [[effect]] table per effect",
+                Style::default().fg(Color::DarkGray),
+            )),
+        ]))
+        .wrap(Wrap { trim: false })
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Yellow)),
+        );
+        f.render_widget(popup, area);
+    }
+
+    if let InputMode::Recording(idx) = app.current_input {
+        let area = centered_rect(60, 40, f.area());
+        f.render_widget(Clear, area);
+        let mut lines = param_lines(recording::SETTINGS, &app.recording, Some(idx), false);
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            "Used by interactive effects: the animation and key log are saved next to the output",
+            Style::default().fg(Color::DarkGray),
+        )));
+        let popup = Paragraph::new(Text::from(lines))
+            .wrap(Wrap { trim: false })
+            .block(
+                Block::default()
+                    .title("Session Recording")
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Yellow)),
+            );
+        f.render_widget(popup, area);
+    }
+
+    if let Some(browser) = &app.browser {
+        browser.render(f, centered_rect(80, 70, f.area()));
+    }
+
+    // Processing overlay; interactive sessions keep the live preview visible
+    if app.processing && app.session.is_none() {
         let area = centered_rect(50, 20, f.area());
         f.render_widget(Clear, area);
         let gauge = Gauge::default()
             .block(
                 Block::default()
-                    .title("Processing...")
+                    .title(if app.cancelling() {
+                        "Cancelling..."
+                    } else {
+                        "Processing..."
+                    })
                     .borders(Borders::ALL)
                     .border_style(Style::default().fg(Color::Green)),
             )
@@ -626,6 +2371,84 @@ fn ui(f: &mut Frame, app: &App) {
     }
 }
 
+/// One line per field of a parameter editor: label, typed value, the default
+/// when it is empty and why the value is unusable. `editing` gets a cursor.
+fn param_lines<'a>(
+    specs: &[ParamSpec],
+    fields: &'a [String],
+    editing: Option<usize>,
+    ranges: bool,
+) -> Vec<Line<'a>> {
+    specs
+        .iter()
+        .zip(fields)
+        .enumerate()
+        .map(|(i, (spec, value))| {
+            let editing = editing == Some(i);
+            let style = if editing {
+                Style::default().fg(Color::Green)
+            } else {
+                Style::default()
+            };
+            let display = if editing {
+                format!("{}▏", value)
+            } else {
+                value.clone()
+            };
+            let mut line = vec![
+                Span::styled(
+                    format!("{}: ", spec.display_label()),
+                    Style::default().fg(Color::Cyan),
+                ),
+                Span::styled(display, style),
+            ];
+            if value.trim().is_empty() {
+                line.push(Span::styled(
+                    format!("{} (default)", spec.format_value(spec.default_value())),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            if let Some(error) = param_error(spec, value, ranges) {
+                line.push(Span::styled(
+                    format!("  ✗ {}", error),
+                    Style::default().fg(Color::Red),
+                ));
+            }
+            Line::from(line)
+        })
+        .collect()
+}
+
+/// One status line for a finished batch or sequence: counts, and the first
+/// failure. `kind` names the run and `noun` what it ran on.
+fn batch_summary(kind: &str, noun: &str, report: &BatchReport) -> String {
+    let (succeeded, failed) = (report.succeeded(), report.failed());
+    let first_failure = report.outcomes.iter().find_map(|outcome| {
+        let error = outcome.result.as_ref().err()?;
+        let name = outcome.input.file_name()?.to_string_lossy();
+        Some(format!(" — first: {}: {}", name, error))
+    });
+    if report.cancelled {
+        format!(
+            "🛑 {} cancelled: {} bent, {} failed before stopping{}",
+            kind,
+            succeeded,
+            failed,
+            first_failure.unwrap_or_default()
+        )
+    } else if failed == 0 {
+        format!("✅ {} done: all {} {} bent", kind, succeeded, noun)
+    } else {
+        format!(
+            "⚠️ {} done: {} bent, {} failed{}",
+            kind,
+            succeeded,
+            failed,
+            first_failure.unwrap_or_default()
+        )
+    }
+}
+
 fn centered_rect(
     percent_x: u16,
     percent_y: u16,
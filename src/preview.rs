@@ -0,0 +1,135 @@
+use image::{DynamicImage, RgbaImage, imageops::FilterType};
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Color, Style},
+    text::Span,
+    widgets::{Block, Paragraph, Wrap},
+};
+
+use crate::libdatabend::imageio;
+
+/// Largest side of the cached thumbnail. Plenty for any terminal, and small
+/// enough to rescale on every frame.
+const THUMBNAIL_SIZE: u32 = 512;
+
+/// A downscaled image shown in the preview panel, or why it couldn't be.
+pub struct Preview {
+    title: String,
+    image: Result<RgbaImage, String>,
+}
+
+impl Preview {
+    /// Decodes `path` for previewing. Failures are kept and shown in the
+    /// panel instead of the image.
+    pub fn load(label: &str, path: &str) -> Self {
+        match imageio::load(path) {
+            Ok(img) => Self::from_image(label, path, &img),
+            Err(error) => Self {
+                title: format!("{}: {}", label, path),
+                image: Err(error.to_string()),
+            },
+        }
+    }
+
+    pub fn from_image(label: &str, path: &str, img: &DynamicImage) -> Self {
+        Self {
+            title: format!("{}: {} ({}×{})", label, path, img.width(), img.height()),
+            image: Ok(img.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).to_rgba8()),
+        }
+    }
+
+    /// Draws the image with upper half blocks: each cell shows two pixels,
+    /// the top one as foreground and the bottom one as background.
+    pub fn render(&self, f: &mut Frame, area: Rect, block: Block, truecolor: bool) {
+        let inner = block.inner(area);
+        f.render_widget(block.title(self.title.as_str()), area);
+
+        let thumbnail = match &self.image {
+            Ok(thumbnail) => thumbnail,
+            Err(error) => {
+                let message = Paragraph::new(Span::styled(
+                    format!("Can't preview: {}", error),
+                    Style::default().fg(Color::DarkGray),
+                ))
+                .wrap(Wrap { trim: true });
+                f.render_widget(message, inner);
+                return;
+            }
+        };
+        if inner.width == 0 || inner.height == 0 {
+            return;
+        }
+
+        // Fit into the panel at two pixels per cell vertically
+        let scale = (inner.width as f32 / thumbnail.width() as f32)
+            .min(inner.height as f32 * 2.0 / thumbnail.height() as f32);
+        let width = ((thumbnail.width() as f32 * scale) as u32).max(1);
+        let height = ((thumbnail.height() as f32 * scale) as u32).max(1);
+        let scaled = image::imageops::resize(thumbnail, width, height, FilterType::Triangle);
+
+        let left = inner.x + (inner.width - width as u16) / 2;
+        let top = inner.y + (inner.height - height.div_ceil(2) as u16) / 2;
+        let buffer = f.buffer_mut();
+        for y in 0..height.div_ceil(2) {
+            for x in 0..width {
+                let upper = color(scaled.get_pixel(x, y * 2).0, truecolor);
+                let lower = match scaled.get_pixel_checked(x, y * 2 + 1) {
+                    Some(pixel) => color(pixel.0, truecolor),
+                    None => Color::Reset,
+                };
+                if let Some(cell) = buffer.cell_mut((left + x as u16, top + y as u16)) {
+                    cell.set_char('▀').set_fg(upper).set_bg(lower);
+                }
+            }
+        }
+    }
+}
+
+/// Whether the terminal says it can show 24-bit colour.
+pub fn truecolor_supported() -> bool {
+    std::env::var("COLORTERM").is_ok_and(|value| {
+        value.eq_ignore_ascii_case("truecolor") || value.eq_ignore_ascii_case("24bit")
+    })
+}
+
+/// Composites a pixel onto black and converts it to a terminal colour.
+fn color([r, g, b, a]: [u8; 4], truecolor: bool) -> Color {
+    let [r, g, b] = [r, g, b].map(|c| (c as u16 * a as u16 / 255) as u8);
+    if truecolor {
+        Color::Rgb(r, g, b)
+    } else {
+        Color::Indexed(ansi256(r, g, b))
+    }
+}
+
+/// Nearest entry of the xterm 256-colour palette, from either the 6×6×6
+/// colour cube or the 24-step grey ramp.
+fn ansi256(r: u8, g: u8, b: u8) -> u8 {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let nearest_level = |c: u8| {
+        (0..LEVELS.len())
+            .min_by_key(|&i| LEVELS[i].abs_diff(c))
+            .unwrap_or_default()
+    };
+    let distance = |(r2, g2, b2): (u8, u8, u8)| {
+        [r.abs_diff(r2), g.abs_diff(g2), b.abs_diff(b2)]
+            .iter()
+            .map(|&d| d as u32 * d as u32)
+            .sum::<u32>()
+    };
+
+    let (ri, gi, bi) = (nearest_level(r), nearest_level(g), nearest_level(b));
+    let cube = (LEVELS[ri], LEVELS[gi], LEVELS[bi]);
+    let cube_index = 16 + 36 * ri + 6 * gi + bi;
+
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let grey_step = (average.saturating_sub(8) / 10).min(23);
+    let grey = (8 + grey_step * 10) as u8;
+
+    if distance((grey, grey, grey)) < distance(cube) {
+        232 + grey_step as u8
+    } else {
+        cube_index as u8
+    }
+}